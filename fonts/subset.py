@@ -0,0 +1,166 @@
+#!/usr/bin/env python3
+"""Subsets a TrueType font to the glyphs of the WinAnsi encoding, the only ones the PDF labels
+use, for embedding in the app: `python3 fonts/subset.py DejaVuSans.ttf DejaVuSans-WinAnsi.ttf`.
+
+Glyphs are renumbered from 0 (the missing glyph) in the order of their codes; the character map,
+the metrics and the outlines are rebuilt to match, the hinting tables and the names are kept, and
+the tables for substitutions, positioning and kerning, which the labels do not use, are left out.
+"""
+import struct
+import sys
+
+# The characters of the codes 0x80 to 0x9F of Windows-1252; the other codes from 0x20 are Latin-1.
+WIN_ANSI_80_9F = '€\0‚ƒ„…†‡ˆ‰Š‹Œ\0Ž\0\0‘’“”•–—˜™š›œ\0žŸ'
+WIN_ANSI = [chr(c) for c in range(0x20, 0x7F)] + [c for c in WIN_ANSI_80_9F if c != '\0'] + [chr(c) for c in range(0xA0, 0x100)]
+KEEP = [b'OS/2', b'cvt ', b'fpgm', b'gasp', b'name', b'prep']
+
+
+def u16(data, offset):
+    return struct.unpack_from('>H', data, offset)[0]
+
+
+def tables(font):
+    result = {}
+    for i in range(u16(font, 4)):
+        tag, _, offset, length = struct.unpack_from('>4sIII', font, 12 + 16 * i)
+        result[tag] = font[offset:offset + length]
+    return result
+
+
+def cmap_glyphs(cmap):
+    """The Unicode format 4 subtable of `cmap`, as a dictionary from code to glyph."""
+    for i in range(u16(cmap, 2)):
+        platform, encoding, offset = struct.unpack_from('>HHI', cmap, 4 + 8 * i)
+        if (platform == 3 and encoding == 1 or platform == 0) and u16(cmap, offset) == 4:
+            table = cmap[offset:]
+            segments = u16(table, 6) // 2
+            ends, starts = 14, 16 + 2 * segments
+            deltas, range_offsets = starts + 2 * segments, starts + 4 * segments
+            glyphs = {}
+            for s in range(segments):
+                start, end = u16(table, starts + 2 * s), u16(table, ends + 2 * s)
+                delta, range_offset = u16(table, deltas + 2 * s), u16(table, range_offsets + 2 * s)
+                for code in range(start, min(end, 0xFFFE) + 1):
+                    if range_offset == 0:
+                        glyph = (code + delta) & 0xFFFF
+                    else:
+                        glyph = u16(table, range_offsets + 2 * s + range_offset + 2 * (code - start))
+                        glyph = (glyph + delta) & 0xFFFF if glyph else 0
+                    glyphs[code] = glyph
+            return glyphs
+    raise ValueError('no Unicode character map in the font')
+
+
+def components(glyph):
+    """The offsets of the glyph indices of the components of a composite glyph."""
+    if len(glyph) < 10 or struct.unpack_from('>h', glyph, 0)[0] >= 0:
+        return []
+    offsets, offset = [], 10
+    while True:
+        flags = u16(glyph, offset)
+        offsets.append(offset + 2)
+        offset += 4 + (4 if flags & 0x0001 else 2)
+        if flags & 0x0008:
+            offset += 2
+        elif flags & 0x0040:
+            offset += 4
+        elif flags & 0x0080:
+            offset += 8
+        if not flags & 0x0020:
+            return offsets
+
+
+def cmap_table(mapping):
+    """A cmap with a single format 4 subtable for Windows Unicode, one segment per character."""
+    codes = sorted(mapping) + [0xFFFF]
+    segments = len(codes)
+    search = 2 ** (segments.bit_length() - 1)
+    subtable = struct.pack('>7H', 4, 0, 0, segments * 2, search * 2, search.bit_length() - 1, 2 * (segments - search))
+    subtable += struct.pack('>%dH' % segments, *codes) + b'\0\0'
+    subtable += struct.pack('>%dH' % segments, *codes)
+    subtable += struct.pack('>%dH' % segments, *[(mapping.get(c, 0) - c) & 0xFFFF for c in codes])
+    subtable += struct.pack('>%dH' % segments, *[0] * segments)
+    subtable = subtable[:2] + struct.pack('>H', len(subtable)) + subtable[4:]
+    return struct.pack('>HHHHI', 0, 1, 3, 1, 12) + subtable
+
+
+def checksum(data):
+    data += b'\0' * (-len(data) % 4)
+    return sum(struct.unpack('>%dI' % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def subset(font):
+    t = tables(font)
+    long_loca = struct.unpack_from('>h', t[b'head'], 50)[0] == 1
+    count = u16(t[b'maxp'], 4)
+    loca = struct.unpack('>%d%s' % (count + 1, 'I' if long_loca else 'H'), t[b'loca'])
+    loca = [offset if long_loca else 2 * offset for offset in loca]
+    outline = lambda g: t[b'glyf'][loca[g]:loca[g + 1]]
+    metrics = u16(t[b'hhea'], 34)
+
+    def metric(glyph):
+        advance = u16(t[b'hmtx'], 4 * min(glyph, metrics - 1))
+        bearing = 4 * glyph + 2 if glyph < metrics else 4 * metrics + 2 * (glyph - metrics)
+        return advance, struct.unpack_from('>h', t[b'hmtx'], bearing)[0]
+
+    by_code = cmap_glyphs(t[b'cmap'])
+    old = [0]
+    for c in WIN_ANSI:
+        glyph = by_code.get(ord(c), 0)
+        if glyph not in old:
+            old.append(glyph)
+    i = 0
+    while i < len(old):
+        for offset in components(outline(old[i])):
+            if u16(outline(old[i]), offset) not in old:
+                old.append(u16(outline(old[i]), offset))
+        i += 1
+    new = {glyph: index for index, glyph in enumerate(old)}
+
+    glyf, offsets, hmtx = b'', [], b''
+    for glyph in old:
+        data = bytearray(outline(glyph))
+        for offset in components(bytes(data)):
+            struct.pack_into('>H', data, offset, new[u16(data, offset)])
+        offsets.append(len(glyf))
+        glyf += bytes(data) + b'\0' * (-len(data) % 4)
+        hmtx += struct.pack('>Hh', *metric(glyph))
+    offsets.append(len(glyf))
+
+    head = bytearray(t[b'head'])
+    struct.pack_into('>I', head, 8, 0)
+    struct.pack_into('>h', head, 50, 1)
+    hhea = bytearray(t[b'hhea'])
+    struct.pack_into('>H', hhea, 34, len(old))
+    maxp = bytearray(t[b'maxp'])
+    struct.pack_into('>H', maxp, 4, len(old))
+    post = bytearray(t[b'post'][:32])
+    struct.pack_into('>I', post, 0, 0x00030000)
+    out = {
+        b'head': bytes(head), b'hhea': bytes(hhea), b'maxp': bytes(maxp), b'post': bytes(post),
+        b'hmtx': hmtx, b'loca': struct.pack('>%dI' % len(offsets), *offsets), b'glyf': glyf,
+        b'cmap': cmap_table({ord(c): new[by_code.get(ord(c), 0)] for c in WIN_ANSI if by_code.get(ord(c), 0)}),
+    }
+    out.update({tag: t[tag] for tag in KEEP if tag in t})
+
+    tags = sorted(out)
+    search = 2 ** (len(tags).bit_length() - 1)
+    header = struct.pack('>IHHHH', 0x00010000, len(tags), search * 16, search.bit_length() - 1, len(tags) * 16 - search * 16)
+    offset = len(header) + 16 * len(tags)
+    directory, body, head_offset = b'', b'', 0
+    for tag in tags:
+        data = out[tag]
+        if tag == b'head':
+            head_offset = offset + len(body)
+        directory += struct.pack('>4sIII', tag, checksum(data), offset + len(body), len(data))
+        body += data + b'\0' * (-len(data) % 4)
+    result = bytearray(header + directory + body)
+    struct.pack_into('>I', result, head_offset + 8, (0xB1B0AFBA - checksum(bytes(result))) & 0xFFFFFFFF)
+    return bytes(result)
+
+
+if __name__ == '__main__':
+    with open(sys.argv[1], 'rb') as source:
+        font = source.read()
+    with open(sys.argv[2], 'wb') as target:
+        target.write(subset(font))
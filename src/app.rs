@@ -1,47 +1,1166 @@
 use yew::prelude::*;
+use yew::services::ConsoleService;
+use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
+use crate::barcode::{self, PackageCode, QrCode, Svg};
+use crate::catalogue::{self, Catalogue, CATALOGUE_KEY};
+use crate::columns::Field;
+use crate::diagnostics::Diagnostic;
+use crate::labelpdf::{self, LabelFonts, LabelOptions, LabelSize, LABELS_KEY};
+use crate::manifest::{self, ManifestRow};
+use crate::money::Money;
+use crate::packing::{Condition, PackRule, PackingOptions, PACKING_KEY};
+use crate::persist;
+use crate::picklist::{self, PickGroupBy};
+use crate::profiles::{Profiles, PROFILES_KEY};
+use crate::reconcile::{reconcile, ReconcileConfig, RECONCILE_KEY};
+use crate::scanning::{Scan, Verification};
+use crate::spreadsheet::{is_spreadsheet, parse_spreadsheet, sheet_names};
+use crate::summary::{self, SummaryColumn, SummaryRow, SummarySort};
+use crate::thermal;
+use crate::wooapi::{is_json, parse_orders_json};
 use crate::woocsv::{parse_csv, WooCommerceRow, WooCommerceRowBuilder, InputData, OrderDetails, OrderDetailsBuilder, OrderItem, OrderItemBuilder, DeliveryDetail, DeliveryDetailBuilder};
 use wasm_bindgen::__rt::std::error::Error;
 
 #[derive(Debug)]
 pub enum Msg {
     UpdateCsv(String),
+    LoadFile(File),
+    FileLoaded(FileData),
+    DragOver,
+    SelectSheet(usize),
     ToggleMultipack,
+    SetRuleKind(usize, usize),
+    SetRuleValue(usize, String),
+    SetRulePackages(usize, String),
+    AddRule,
+    RemoveRule(usize),
+    MoveRuleUp(usize),
+    ResetRules,
+    SetMaxWeight(String),
+    SetMaxVolume(String),
+    ToggleSplitLines,
+    SetCatalogue(String),
+    LoadCatalogue(File),
+    CatalogueLoaded(FileData),
+    SetTemperatureRules(String),
+    SetNameMappings(String),
+    SetBundles(String),
+    ToggleExpandBundles,
+    ToggleBundleBreakdown,
+    SortSummary(SummaryColumn),
+    SetPrintMode(PrintMode),
+    SetLabelLayout(usize),
+    SetSkipLabels(String),
+    SetCustomSheets(String),
+    MakePdf,
+    SetPickGroupBy(PickGroupBy),
+    SetWalkPath(String),
+    SetZoneRules(String),
+    SetScanText(String),
+    ScanPackage,
+    ResetScans,
+    ToggleDropUnits,
+    SetDropPhrases(String),
+    SetMaxNameLength(String),
+    SelectProfile(usize),
+    SetProfileHeader(Field, String),
+    UpdateNewProfileName(String),
+    AddProfile,
+    RemoveProfile,
+    SetDefaultVat(String),
+    SetTolerance(String),
+    SetVatRules(String),
 }
 
 pub struct Gui {
     link: ComponentLink<Self>,
+    input: Vec<u8>,
+    file: Option<LoadedFile>,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+    sheets: Vec<String>,
+    sheet: Option<String>,
     input_data: Option<InputData>,
-    multipack: bool,
+    packing: PackingOptions,
+    catalogue: Catalogue,
+    catalogue_text: String,
+    catalogue_error: Option<String>,
+    catalogue_task: Option<ReaderTask>,
+    temperature_rules_text: String,
+    name_mappings_text: String,
+    bundles_text: String,
+    summary_sort: SummarySort,
+    print_mode: PrintMode,
+    label_options: LabelOptions,
+    custom_sheets_text: String,
+    /// Labels already used on the first sheet.
+    skip_labels: usize,
+    /// The labels of the orders, made again when the orders, the packing rules or the catalogue
+    /// change, with the files downloaded from them.
+    labels: Vec<OrderDetails>,
+    /// The labels for thermal printers, in ZPL and in EPL.
+    zpl_url: String,
+    epl_url: String,
+    manifest: Vec<ManifestRow>,
+    manifest_url: String,
+    /// The summary of the products, sorted, made again with the labels and when sorted.
+    summary: Vec<SummaryRow>,
+    summary_url: String,
+    /// The labels as a PDF, made on request and dropped when the labels change.
+    pdf_url: Option<String>,
+    /// The fonts of the PDF labels, parsed for the first PDF.
+    label_fonts: Option<LabelFonts>,
+    walk_path_text: String,
+    zone_rules_text: String,
+    /// The packages scanned at loading.
+    verification: Verification,
+    /// What the barcode scanner typed so far, until it presses Enter.
+    scan_text: String,
+    drop_phrases_text: String,
+    profiles: Profiles,
+    new_profile_name: String,
+    reconcile: ReconcileConfig,
+    vat_rules_text: String,
     error: Option<Box<dyn Error>>,
 }
 
+/// What the page shows below the settings, and so what gets printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrintMode {
+    Labels,
+    PackingSlips,
+    PickList,
+    Manifest,
+    /// Scanning the packages at loading, to tick them off against the orders.
+    Scanning,
+}
+
+/// A file chosen with the file picker or dropped on the page.
+struct LoadedFile {
+    name: String,
+    size: u64,
+}
+
+const SUPPORTED_FILES: [&str; 6] = [".csv", ".txt", ".xlsx", ".xls", ".ods", ".json"];
+
+impl Gui {
+    fn parse(&mut self) {
+        let parsed = if is_spreadsheet(&self.input) {
+            parse_spreadsheet(&self.input, self.sheet.as_deref(), self.profiles.selected())
+        } else if is_json(&self.input) {
+            parse_orders_json(&self.input)
+        } else {
+            parse_csv(&self.input, self.profiles.selected())
+        };
+        self.reset_scans();
+        match parsed {
+            Ok(data) => {
+                self.input_data = Some(data);
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(e.into());
+                self.input_data = None;
+            }
+        }
+        self.labels_changed();
+    }
+
+    /// Makes the labels, and the files to download made from them, again; the PDF is dropped, to
+    /// be made on request.
+    fn labels_changed(&mut self) {
+        self.pdf_url = None;
+        self.labels = self.input_data.as_ref().map(|d| d.labels(&self.packing, &self.catalogue)).unwrap_or_default();
+        self.zpl_url = data_url("text/plain", &thermal::zpl(&self.labels));
+        self.epl_url = base64_data_url("application/octet-stream", &thermal::epl(&self.labels));
+        self.manifest = manifest::manifest(&self.labels);
+        self.manifest_url = data_url("text/csv", &manifest::to_csv(&self.manifest));
+        self.summary_changed();
+    }
+
+    fn summary_changed(&mut self) {
+        self.summary = self.input_data.as_ref().map(|d| d.summary(&self.catalogue.bundles)).unwrap_or_default();
+        self.summary_sort.sort(&mut self.summary);
+        self.summary_url = data_url("text/csv", &summary::to_csv(&self.summary));
+    }
+
+    fn load_file(&mut self, file: File) {
+        let name = file.name();
+        if !SUPPORTED_FILES.iter().any(|ext| name.to_lowercase().ends_with(ext)) {
+            self.error = Some(format!("Unsupported file {}: please use one of {}", name, SUPPORTED_FILES.join(", ")).into());
+            return;
+        }
+        self.file = Some(LoadedFile { name, size: file.size() as u64 });
+        match self.reader.read_file(file, self.link.callback(Msg::FileLoaded)) {
+            Ok(task) => self.reader_task = Some(task),
+            Err(e) => self.error = Some(e.into()),
+        }
+    }
+
+    fn file_view(&self) -> Html {
+        let dropped_file = |e: DragEvent| {
+            e.prevent_default();
+            e.data_transfer()
+                .and_then(|transfer| transfer.files())
+                .and_then(|files| files.get(0))
+                .map(Msg::LoadFile)
+                .into_iter()
+                .collect()
+        };
+        html! {
+            <div class="drop-zone"
+                ondragover=self.link.callback(|e: DragEvent| { e.prevent_default(); Msg::DragOver })
+                ondrop=self.link.batch_callback(dropped_file)>
+                <span>{"Drop a .csv, .txt, .xlsx, .ods or .json file here, or choose one: "}</span>
+                <input type="file" accept=SUPPORTED_FILES.join(",") onchange=self.link.batch_callback(|e: ChangeData| match e {
+                    ChangeData::Files(files) => files.get(0).map(Msg::LoadFile).into_iter().collect(),
+                    _ => vec![],
+                })/>
+                {
+                    self.file.as_ref().map(|file| html! {
+                        <div class="file-info">{
+                            format!("{}: {}, {} rows", file.name, format_size(file.size),
+                                self.input_data.as_ref().map(|d| d.data.len()).unwrap_or(0))
+                        }</div>
+                    }).unwrap_or(html! {<div/>})
+                }
+                {
+                    if self.sheets.len() > 1 {
+                        html! {
+                            <div class="sheets">
+                                <label for="select-sheet">{"Sheet: "}</label>
+                                <select id="select-sheet" onchange=self.link.callback(|e: ChangeData| match e {
+                                    ChangeData::Select(select) => Msg::SelectSheet(select.selected_index() as usize),
+                                    _ => Msg::SelectSheet(0),
+                                })> {
+                                    self.sheets.iter().map(|sheet| html! {
+                                        <option selected={self.sheet.as_ref() == Some(sheet)}>{sheet}</option>
+                                    }).collect::<Html>()
+                                } </select>
+                            </div>
+                        }
+                    } else {
+                        html! {<div/>}
+                    }
+                }
+            </div>
+        }
+    }
+
+    fn diagnostics_view(diagnostics: &[Diagnostic]) -> Html {
+        if diagnostics.is_empty() {
+            return html! {<div/>};
+        }
+        let record_view = |d: &Diagnostic| {
+            let mut marked = false;
+            d.record.iter().map(|value| {
+                if d.column.is_some() && !marked && *value == d.raw {
+                    marked = true;
+                    html! {<span class="value"><mark>{value}</mark></span>}
+                } else {
+                    html! {<span class="value">{value}</span>}
+                }
+            }).collect::<Html>()
+        };
+        html! {
+            <div class="diagnostics">
+                <h3>{format!("Problems found in the export: {}", diagnostics.len())}</h3>
+                <p>{"Rows with errors are left out of the labels: fix them in the export and load it again."}</p>
+                <table>
+                    <thead>
+                        <tr>
+                            <th align="left">{"Line"}</th>
+                            <th align="left">{"Column"}</th>
+                            <th align="left">{"Value"}</th>
+                            <th align="left">{"Problem"}</th>
+                            <th align="left">{"Row"}</th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        diagnostics.iter().map(|d| html! {
+                            <tr class=if d.is_error() { "diagnostic-error" } else { "diagnostic-warning" }>
+                                <td>{d.line}</td>
+                                <td>{d.column.as_deref().unwrap_or("")}</td>
+                                <td>{&d.raw}</td>
+                                <td>{format!("{}: {}", d.severity, d.message)}</td>
+                                <td class="record">{record_view(d)}</td>
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+            </div>
+        }
+    }
+
+    fn reconcile_view(&self, labels: &[OrderDetails]) -> Html {
+        use yew::InputData;
+        let mismatches = reconcile(labels, &self.reconcile);
+        html! {
+            <details class="reconcile">
+                <summary>{format!("Order totals not matching their items: {}", mismatches.len())}</summary>
+                <div>
+                    <label for="default-vat">{"VAT added to item prices (%): "}</label>
+                    <input type="number" id="default-vat" min="0" value=self.reconcile.default_vat.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::SetDefaultVat(e.value))/>
+                    <label for="tolerance">{" Tolerance (€): "}</label>
+                    <input type="text" id="tolerance" value=self.reconcile.tolerance.to_string()
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Value(value) => Msg::SetTolerance(value),
+                            _ => Msg::SetTolerance(String::new()),
+                        })/>
+                </div>
+                <div>{"VAT for specific products, one \"KEYWORD: percent\" per line:"}</div>
+                <textarea rows="4" cols="60" value=&self.vat_rules_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetVatRules(e.value))/>
+                <table>
+                    <thead>
+                        <tr>
+                            <th align="left">{"Order"}</th>
+                            <th align="left">{"Customer"}</th>
+                            <th align="right">{"Declared total"}</th>
+                            <th align="right">{"Items"}</th>
+                            <th align="right">{"VAT"}</th>
+                            <th align="right">{"Shipping"}</th>
+                            <th align="right">{"Difference"}</th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        mismatches.iter().map(|r| html! {
+                            <tr class="diagnostic-error">
+                                <td>{r.order_id}</td>
+                                <td>{&r.customer_name}</td>
+                                <td align="right">{format!("{} €", r.declared)}</td>
+                                <td align="right">{format!("{} €", r.items)}</td>
+                                <td align="right">{format!("{} €", r.vat)}</td>
+                                <td align="right">{format!("{} €", r.shipping)}</td>
+                                <td align="right"><b>{format!("{} €", r.difference())}</b></td>
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+            </details>
+        }
+    }
+
+    fn packing_view(&self) -> Html {
+        html! {
+            <details class="packing-rules">
+                <summary>{"Multi-pack rules"}</summary>
+                <p>{"The first rule that matches an order gives its number of packages; orders matching no rule get one package."}</p>
+                <table>
+                    <thead>
+                        <tr>
+                            <th align="left">{"Condition"}</th>
+                            <th align="left">{"Value"}</th>
+                            <th align="left">{"Packages"}</th>
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        self.packing.rules.iter().enumerate().map(|(i, rule)| html! {
+                            <tr>
+                                <td>
+                                    <select onchange=self.link.callback(move |e: ChangeData| match e {
+                                        ChangeData::Select(select) => Msg::SetRuleKind(i, select.selected_index() as usize),
+                                        _ => Msg::SetRuleKind(i, 0),
+                                    })> {
+                                        Condition::KINDS.iter().enumerate().map(|(kind, name)| html! {
+                                            <option selected={kind == rule.condition.kind()}>{name}</option>
+                                        }).collect::<Html>()
+                                    } </select>
+                                </td>
+                                <td>
+                                    <input type="text" value=rule.condition.value() disabled={rule.condition == Condition::Always}
+                                        onchange=self.link.callback(move |e: ChangeData| match e {
+                                            ChangeData::Value(value) => Msg::SetRuleValue(i, value),
+                                            _ => Msg::SetRuleValue(i, String::new()),
+                                        })/>
+                                </td>
+                                <td>
+                                    <input type="number" min="1" value=rule.packages.to_string()
+                                        onchange=self.link.callback(move |e: ChangeData| match e {
+                                            ChangeData::Value(value) => Msg::SetRulePackages(i, value),
+                                            _ => Msg::SetRulePackages(i, String::new()),
+                                        })/>
+                                </td>
+                                <td>
+                                    <button onclick=self.link.callback(move |_| Msg::MoveRuleUp(i)) disabled={i == 0}>{"↑"}</button>
+                                    <button onclick=self.link.callback(move |_| Msg::RemoveRule(i))>{"Remove"}</button>
+                                </td>
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+                <button onclick=self.link.callback(|_| Msg::AddRule)>{"Add rule"}</button>
+                <button onclick=self.link.callback(|_| Msg::ResetRules)>{"Restore default rules"}</button>
+                <div>
+                    <label for="max-weight">{"Max weight per package (kg): "}</label>
+                    <input type="text" id="max-weight" placeholder="no limit"
+                        value=self.packing.max_weight.map(catalogue::format_thousandths).unwrap_or_default()
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Value(value) => Msg::SetMaxWeight(value),
+                            _ => Msg::SetMaxWeight(String::new()),
+                        })/>
+                    <label for="max-volume">{" Max volume per package (l): "}</label>
+                    <input type="text" id="max-volume" placeholder="no limit"
+                        value=self.packing.max_volume.map(catalogue::format_thousandths).unwrap_or_default()
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Value(value) => Msg::SetMaxVolume(value),
+                            _ => Msg::SetMaxVolume(String::new()),
+                        })/>
+                </div>
+                <div>
+                    <input type="checkbox" id="checkbox-split-lines" checked={self.packing.split_lines}
+                        onclick=self.link.callback(|_| Msg::ToggleSplitLines)/>
+                    <label for="checkbox-split-lines">{"Split high-quantity lines between packages"}</label>
+                </div>
+            </details>
+        }
+    }
+
+    fn catalogue_view(&self) -> Html {
+        use yew::InputData;
+        let products = self.input_data.as_ref().map(|d| self.catalogue.index(&d.data));
+        let unmatched = products.as_ref().map(|p| p.unmatched()).unwrap_or_default();
+        let unclassified = products.as_ref().map(|p| p.unclassified()).unwrap_or_default();
+        let fuzzy = products.as_ref().map(|p| p.fuzzy()).unwrap_or_default();
+        html! {
+            <details class="catalogue">
+                <summary>{format!("Product catalogue: {} products, {} products of the orders not in it", self.catalogue.products.len(), unmatched.len())}</summary>
+                <div>
+                    <span>{"Import a .csv or .json catalogue: "}</span>
+                    <input type="file" accept=".csv,.txt,.json" onchange=self.link.batch_callback(|e: ChangeData| match e {
+                        ChangeData::Files(files) => files.get(0).map(Msg::LoadCatalogue).into_iter().collect(),
+                        _ => vec![],
+                    })/>
+                </div>
+                <div>{"Or edit it below, as CSV with a header; only the Name column is required. Weights are in kg, volumes in litres, temperatures are frozen, chilled or ambient. Products are found by SKU, or else by name:"}</div>
+                <textarea rows="8" cols="120" value=&self.catalogue_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetCatalogue(e.value))/>
+                {
+                    self.catalogue_error.as_ref().map(|e| html! {
+                        <div class="error">{e}</div>
+                    }).unwrap_or_default()
+                }
+                <div>{"Temperature of the products not in the catalogue, one \"KEYWORD: frozen\", \"KEYWORD: chilled\" or \"KEYWORD: ambient\" per line:"}</div>
+                <textarea rows="4" cols="60" value=&self.temperature_rules_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetTemperatureRules(e.value))/>
+                <h3>{format!("Products of the orders not in the catalogue: {}", unmatched.len())}</h3>
+                <ul> {
+                    unmatched.iter().map(|name| html! { <li>{name}</li> }).collect::<Html>()
+                } </ul>
+                <h3>{format!("Products of the orders with no temperature, printed with no banner: {}", unclassified.len())}</h3>
+                <ul> {
+                    unclassified.iter().map(|name| html! { <li>{name}</li> }).collect::<Html>()
+                } </ul>
+                <h3>{format!("Products found by a similar name, to be checked: {}", fuzzy.len())}</h3>
+                <table>
+                    <thead>
+                        <tr>
+                            <th align="left">{"In the orders"}</th>
+                            <th align="left">{"In the catalogue"}</th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        fuzzy.iter().map(|(name, product)| html! {
+                            <tr><td>{name}</td><td>{&product.name}</td></tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+            </details>
+        }
+    }
+
+    fn short_names_view(&self) -> Html {
+        use yew::InputData;
+        let short_names = &self.catalogue.short_names;
+        let products = self.input_data.as_ref().map(|d| self.catalogue.index(&d.data));
+        let names = products.as_ref().map(|p| p.label_names()).unwrap_or_default();
+        html! {
+            <details class="short-names">
+                <summary>{"Short names on labels"}</summary>
+                <div>{"Long product names can be shortened on the labels; the summary keeps them whole. The short name in the catalogue is used if there is one; names can also be mapped one by one, \"NAME => SHORT NAME\" per line:"}</div>
+                <textarea rows="4" cols="100" value=&self.name_mappings_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetNameMappings(e.value))/>
+                <div>{"Other names are shortened by these rules."}</div>
+                <div>
+                    <input type="checkbox" id="checkbox-drop-units" checked={short_names.drop_units}
+                        onclick=self.link.callback(|_| Msg::ToggleDropUnits)/>
+                    <label for="checkbox-drop-units">{"Leave out quantities like 500 g or 1,5 l"}</label>
+                </div>
+                <div>{"Words or phrases to leave out, one per line:"}</div>
+                <textarea rows="4" cols="60" value=&self.drop_phrases_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetDropPhrases(e.value))/>
+                <div>
+                    <label for="max-name-length">{"Maximum length (0 for no limit): "}</label>
+                    <input type="number" id="max-name-length" min="0" value=short_names.max_length.to_string()
+                        oninput=self.link.callback(|e: InputData| Msg::SetMaxNameLength(e.value))/>
+                </div>
+                <table>
+                    <thead>
+                        <tr>
+                            <th align="left">{"Name in the orders"}</th>
+                            <th align="left">{"Name on the labels"}</th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        names.iter().map(|(name, short)| html! {
+                            <tr>
+                                <td>{name}</td>
+                                { if *name == short.as_str() { html! { <td>{short}</td> } } else { html! { <td><b>{short}</b></td> } } }
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+            </details>
+        }
+    }
+
+    fn bundles_view(&self) -> Html {
+        use yew::InputData;
+        let bundles = &self.catalogue.bundles;
+        html! {
+            <details class="bundles">
+                <summary>{format!("Bundles: {}", bundles.bundles.len())}</summary>
+                <div>{"Products sold as a box of other products: the name of each bundle on a line, followed by its components, one \"quantity NAME\" per line, indented:"}</div>
+                <textarea rows="8" cols="80" value=&self.bundles_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetBundles(e.value))/>
+                <div>
+                    <input type="checkbox" id="checkbox-expand-bundles" checked={bundles.expand}
+                        onclick=self.link.callback(|_| Msg::ToggleExpandBundles)/>
+                    <label for="checkbox-expand-bundles">{"Count the components of bundles in the summary and the pick list"}</label>
+                </div>
+                <div>
+                    <input type="checkbox" id="checkbox-bundle-breakdown" checked={bundles.breakdown_on_labels}
+                        onclick=self.link.callback(|_| Msg::ToggleBundleBreakdown)/>
+                    <label for="checkbox-bundle-breakdown">{"List the components of bundles on the labels"}</label>
+                </div>
+            </details>
+        }
+    }
+
+    fn summary_view(&self) -> Html {
+        let rows = &self.summary;
+        let methods = summary::delivery_methods(rows);
+        let header = |column: SummaryColumn, name: &str, align: &str| html! {
+            <th class="sortable" align=align onclick=self.link.callback(move |_| Msg::SortSummary(column))>
+                {format!("{}{}", name, self.summary_sort.arrow(column))}
+            </th>
+        };
+        html! {
+            <div class="summary">
+                <a class="export" href=self.summary_url.as_str() download="riepilogo.csv">{"Export as CSV"}</a>
+                <table>
+                    <thead>
+                        <tr>
+                            { header(SummaryColumn::Product, "Prodotto", "left") }
+                            { header(SummaryColumn::Quantity, "Quantità", "right") }
+                            { header(SummaryColumn::Orders, "Ordini", "right") }
+                            { header(SummaryColumn::Revenue, "Incasso", "right") }
+                            { methods.iter().map(|method| html! { <th align="right">{method}</th> }).collect::<Html>() }
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        rows.iter().map(|row| html! {
+                            <tr>
+                                <td>{&row.product_name}</td>
+                                <td align="right">{row.total_quantity}</td>
+                                <td align="right">{row.orders}</td>
+                                <td align="right">{format!("{} €", row.revenue)}</td>
+                                {
+                                    methods.iter()
+                                        .map(|method| html! { <td align="right">{row.by_delivery.get(method).copied().unwrap_or(0)}</td> })
+                                        .collect::<Html>()
+                                }
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+            </div>
+        }
+    }
+
+    fn pick_list_options_view(&self) -> Html {
+        use yew::InputData;
+        let options = &self.catalogue.pick_list;
+        html! {
+            <details class="pick-list-options">
+                <summary>{"Pick list"}</summary>
+                <div>
+                    <span>{"Group the products by: "}</span>
+                    <input type="radio" id="radio-group-producer" name="pick-group-by" checked={options.group_by == PickGroupBy::Producer}
+                        onclick=self.link.callback(|_| Msg::SetPickGroupBy(PickGroupBy::Producer))/>
+                    <label for="radio-group-producer">{"Producer"}</label>
+                    <input type="radio" id="radio-group-location" name="pick-group-by" checked={options.group_by == PickGroupBy::Location}
+                        onclick=self.link.callback(|_| Msg::SetPickGroupBy(PickGroupBy::Location))/>
+                    <label for="radio-group-location">{"Location"}</label>
+                </div>
+                <div>{"Walk path: the producers or locations in the order the pickers walk past them, one per line. A location also covers the ones starting with it, e.g. A covers A1 and A2:"}</div>
+                <textarea rows="6" cols="60" value=&self.walk_path_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetWalkPath(e.value))/>
+                <div>{"Producer or location of the products not in the catalogue, one \"KEYWORD: zone\" per line:"}</div>
+                <textarea rows="4" cols="60" value=&self.zone_rules_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetZoneRules(e.value))/>
+            </details>
+        }
+    }
+
+    fn print_mode_view(&self) -> Html {
+        html! {
+            <div class="print-mode">
+                <span>{"Show and print: "}</span>
+                <input type="radio" id="radio-print-labels" name="print-mode" checked={self.print_mode == PrintMode::Labels}
+                    onclick=self.link.callback(|_| Msg::SetPrintMode(PrintMode::Labels))/>
+                <label for="radio-print-labels">{"Labels"}</label>
+                <input type="radio" id="radio-print-packing-slips" name="print-mode" checked={self.print_mode == PrintMode::PackingSlips}
+                    onclick=self.link.callback(|_| Msg::SetPrintMode(PrintMode::PackingSlips))/>
+                <label for="radio-print-packing-slips">{"Packing slips"}</label>
+                <input type="radio" id="radio-print-pick-list" name="print-mode" checked={self.print_mode == PrintMode::PickList}
+                    onclick=self.link.callback(|_| Msg::SetPrintMode(PrintMode::PickList))/>
+                <label for="radio-print-pick-list">{"Pick list"}</label>
+                <input type="radio" id="radio-print-manifest" name="print-mode" checked={self.print_mode == PrintMode::Manifest}
+                    onclick=self.link.callback(|_| Msg::SetPrintMode(PrintMode::Manifest))/>
+                <label for="radio-print-manifest">{"Delivery sheet"}</label>
+                <input type="radio" id="radio-print-scanning" name="print-mode" checked={self.print_mode == PrintMode::Scanning}
+                    onclick=self.link.callback(|_| Msg::SetPrintMode(PrintMode::Scanning))/>
+                <label for="radio-print-scanning">{"Scan packages"}</label>
+            </div>
+        }
+    }
+
+    /// The input the barcode scanner types into, as a keyboard ending each code with Enter, the
+    /// orders with the packages still missing, and the scans to look at.
+    fn scanning_view(&self, labels: &[OrderDetails]) -> Html {
+        use yew::InputData;
+        let progress = self.verification.progress(labels);
+        let packages = progress.iter().map(|order| order.packages).sum::<usize>();
+        let missing = progress.iter().map(|order| order.missing.len()).sum::<usize>();
+        let complete = progress.iter().filter(|order| order.is_complete()).count();
+        let scan_class = |scan: &Scan| if scan.is_warning() { "scan scan-warning" } else { "scan scan-ok" };
+        html! {
+            <div class="scanning">
+                <h2>{"Verifica colli"}</h2>
+                <input type="text" class="scan-input" placeholder="Scan a package" autofocus=true value=&self.scan_text
+                    oninput=self.link.callback(|e: InputData| Msg::SetScanText(e.value))
+                    onkeypress=self.link.batch_callback(|e: KeyboardEvent| if e.key() == "Enter" { vec![Msg::ScanPackage] } else { vec![] })/>
+                <button onclick=self.link.callback(|_| Msg::ResetScans)>{"Start again"}</button>
+                {
+                    self.verification.scans.last().map(|scan| html! {
+                        <div class=scan_class(scan)>{scan.to_string()}</div>
+                    }).unwrap_or(html! {})
+                }
+                <p>{format!("{} of {} packages scanned, {} of {} orders complete", packages - missing, packages, complete, progress.len())}</p>
+                <table class="scan-progress">
+                    <thead>
+                        <tr>
+                            <th>{"Ordine"}</th>
+                            <th>{"Cliente"}</th>
+                            <th>{"Colli"}</th>
+                            <th>{"Mancanti"}</th>
+                        </tr>
+                    </thead>
+                    <tbody> {
+                        progress.iter().map(|order| html! {
+                            <tr class=if order.is_complete() { "complete" } else { "incomplete" }>
+                                <td>{order.order_id}</td>
+                                <td>{&order.customer_name}</td>
+                                <td>{format!("{} di {}", order.packages - order.missing.len(), order.packages)}</td>
+                                <td>{
+                                    if order.is_complete() {
+                                        "completo".to_owned()
+                                    } else {
+                                        order.missing.iter()
+                                            .map(|&package| PackageCode { order_id: order.order_id, package, packages: order.packages }.to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    }
+                                }</td>
+                            </tr>
+                        }).collect::<Html>()
+                    } </tbody>
+                </table>
+                {
+                    if self.verification.scans.iter().any(Scan::is_warning) {
+                        html! {
+                            <div class="scan-warnings">
+                                <h3>{"Warnings"}</h3>
+                                <ul> {
+                                    self.verification.scans.iter().filter(|scan| scan.is_warning()).map(|scan| html! {
+                                        <li>{scan.to_string()}</li>
+                                    }).collect::<Html>()
+                                } </ul>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+
+    fn pdf_view(&self) -> Html {
+        use yew::InputData;
+        let sheets = self.label_options.sheets();
+        let on_sheet = self.label_options.sheet.is_some();
+        html! {
+            <div class="pdf">
+                <label for="select-label-layout">{"PDF labels on: "}</label>
+                <select id="select-label-layout" onchange=self.link.callback(|e: ChangeData| match e {
+                    ChangeData::Select(select) => Msg::SetLabelLayout(select.selected_index() as usize),
+                    _ => Msg::SetLabelLayout(0),
+                })> {
+                    LabelSize::ALL.iter().map(|size| html! {
+                        <option selected={!on_sheet && *size == self.label_options.size}>{format!("one label per page, {}", size.name())}</option>
+                    }).collect::<Html>()
+                } {
+                    sheets.iter().map(|sheet| html! {
+                        <option selected={self.label_options.sheet.as_ref() == Some(&sheet.name)}>{&sheet.name}</option>
+                    }).collect::<Html>()
+                } </select>
+                {
+                    if on_sheet {
+                        html! {
+                            <span>
+                                <label for="skip-labels">{" Labels already used on the first sheet: "}</label>
+                                <input type="number" id="skip-labels" min="0" value=self.skip_labels.to_string()
+                                    oninput=self.link.callback(|e: InputData| Msg::SetSkipLabels(e.value))/>
+                            </span>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                <button onclick=self.link.callback(|_| Msg::MakePdf)>{"Make PDF"}</button>
+                {
+                    self.pdf_url.as_ref().map(|url| html! {
+                        <a href=url.as_str() download="etichette.pdf">{"Download etichette.pdf"}</a>
+                    }).unwrap_or_default()
+                }
+                <details class="custom-sheets">
+                    <summary>{"Other sheets of labels"}</summary>
+                    <div>{"One sheet per line, in millimetres: \"NAME; page width x height; columns x rows; label width x height; top margin; left margin; gap between columns; gap between rows\", e.g. \"Ufficio; 210x297; 3x8; 70x37; 0,5; 0; 0; 0\". Labels less than 90 mm tall only have the address:"}</div>
+                    <textarea rows="4" cols="100" value=&self.custom_sheets_text
+                        oninput=self.link.callback(|e: InputData| Msg::SetCustomSheets(e.value))/>
+                </details>
+            </div>
+        }
+    }
+
+    fn pick_list_view(&self, data: &InputData) -> Html {
+        html! {
+            <div class="pick-list"> {
+                picklist::pick_list(&data.data, &self.catalogue).iter().map(|group| html! {
+                    <div class="pick-group">
+                        <h3>{if group.zone.is_empty() { "Altro" } else { &group.zone }}</h3>
+                        <table>
+                            <thead>
+                                <tr>
+                                    <th></th>
+                                    <th align="right">{"Quantità"}</th>
+                                    <th align="left">{"Prodotto"}</th>
+                                    <th align="left">{"Ubicazione"}</th>
+                                </tr>
+                            </thead>
+                            <tbody> {
+                                group.lines.iter().map(|line| html! {
+                                    <tr>
+                                        <td class="tick"><input type="checkbox"/></td>
+                                        <td align="right">{line.quantity}</td>
+                                        <td>{&line.product_name}</td>
+                                        <td>{&line.location}</td>
+                                    </tr>
+                                }).collect::<Html>()
+                            } </tbody>
+                        </table>
+                    </div>
+                }).collect::<Html>()
+            } </div>
+        }
+    }
+
+    fn load_catalogue(&mut self, data: &[u8]) {
+        match self.catalogue.import(data) {
+            Ok(()) => {
+                self.catalogue_text = self.catalogue.text();
+                self.catalogue_error = None;
+                self.catalogue_changed();
+                self.reset_scans();
+            }
+            Err(e) => self.catalogue_error = Some(format!("Could not import the catalogue: {}", e)),
+        }
+    }
+
+    fn profiles_changed(&mut self) {
+        persist::save(PROFILES_KEY, &self.profiles);
+        self.parse();
+    }
+
+    fn packing_changed(&mut self) {
+        persist::save(PACKING_KEY, &self.packing);
+        self.labels_changed();
+        self.reset_scans();
+    }
+
+    fn catalogue_changed(&mut self) {
+        persist::save(CATALOGUE_KEY, &self.catalogue);
+        self.labels_changed();
+    }
+
+    fn label_options_changed(&mut self) {
+        persist::save(LABELS_KEY, &self.label_options);
+        self.pdf_url = None;
+    }
+
+    /// Forgets the packages scanned, when the orders or their packages may have changed: a code
+    /// scanned before could now tick off another package.
+    fn reset_scans(&mut self) {
+        self.verification = Verification::default();
+        self.scan_text.clear();
+    }
+
+    fn profiles_view(&self) -> Html {
+        use yew::InputData;
+        let selected = self.profiles.selected();
+        html! {
+            <div class="profiles">
+                <label for="select-profile">{"Column profile: "}</label>
+                <select id="select-profile" onchange=self.link.callback(|e: ChangeData| match e {
+                    ChangeData::Select(select) => Msg::SelectProfile(select.selected_index() as usize),
+                    _ => Msg::SelectProfile(0),
+                })> {
+                    self.profiles.profiles.iter().enumerate().map(|(i, profile)| html! {
+                        <option selected={i == self.profiles.selected}>{&profile.name}</option>
+                    }).collect::<Html>()
+                } </select>
+                <input type="text" placeholder="New profile name" value=&self.new_profile_name
+                    oninput=self.link.callback(|e: InputData| Msg::UpdateNewProfileName(e.value))/>
+                <button onclick=self.link.callback(|_| Msg::AddProfile)>{"Add profile"}</button>
+                <button onclick=self.link.callback(|_| Msg::RemoveProfile)>{"Remove profile"}</button>
+                <details>
+                    <summary>{format!("Columns of \"{}\"", selected.name)}</summary>
+                    <table>
+                        <thead>
+                            <tr>
+                                <th align="left">{"Field"}</th>
+                                <th align="left">{"Header in the export"}</th>
+                            </tr>
+                        </thead>
+                        <tbody> {
+                            Field::ALL.iter().map(|&field| html! {
+                                <tr>
+                                    <td>{if field.is_required() { format!("{} *", field) } else { field.to_string() }}</td>
+                                    <td>
+                                        <input type="text"
+                                            value=selected.header(field).unwrap_or("")
+                                            placeholder=field.aliases().join(" / ")
+                                            oninput=self.link.callback(move |e: InputData| Msg::SetProfileHeader(field, e.value))/>
+                                    </td>
+                                </tr>
+                            }).collect::<Html>()
+                        } </tbody>
+                    </table>
+                </details>
+            </div>
+        }
+    }
+}
+
 impl Component for Gui {
     type Message = Msg;
     type Properties = ();
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Gui { link, input_data: None, multipack: true, error: None }
+        let profiles = persist::load(PROFILES_KEY).map(Profiles::checked).unwrap_or_default();
+        let reconcile: ReconcileConfig = persist::load(RECONCILE_KEY).unwrap_or_default();
+        let catalogue: Catalogue = persist::load(CATALOGUE_KEY).unwrap_or_default();
+        let label_options: LabelOptions = persist::load(LABELS_KEY).unwrap_or_default();
+        Gui {
+            link,
+            input: Vec::new(),
+            file: None,
+            reader: ReaderService::new(),
+            reader_task: None,
+            sheets: Vec::new(),
+            sheet: None,
+            input_data: None,
+            packing: persist::load(PACKING_KEY).unwrap_or_default(),
+            catalogue_text: catalogue.text(),
+            catalogue_error: None,
+            catalogue_task: None,
+            temperature_rules_text: catalogue.temperature_rules_text(),
+            name_mappings_text: catalogue.short_names.mappings_text(),
+            bundles_text: catalogue.bundles.text(),
+            summary_sort: SummarySort::default(),
+            print_mode: PrintMode::Labels,
+            custom_sheets_text: label_options.custom_sheets_text(),
+            label_options,
+            skip_labels: 0,
+            labels: Vec::new(),
+            zpl_url: String::new(),
+            epl_url: String::new(),
+            manifest: Vec::new(),
+            manifest_url: String::new(),
+            summary: Vec::new(),
+            summary_url: String::new(),
+            pdf_url: None,
+            label_fonts: None,
+            walk_path_text: catalogue.pick_list.walk_path_text(),
+            zone_rules_text: catalogue.pick_list.zone_rules_text(),
+            verification: Verification::default(),
+            scan_text: String::new(),
+            drop_phrases_text: catalogue.short_names.drop_phrases_text(),
+            catalogue,
+            profiles,
+            new_profile_name: String::new(),
+            vat_rules_text: reconcile.vat_rules_text(),
+            reconcile,
+            error: None,
+        }
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
             Msg::UpdateCsv(data) => {
-                stdweb::console!(log, "Received update csv:", &data);
-                let parsed = parse_csv(&data);
-                match parsed {
-                    Ok(data) => {
-                        self.input_data = Some(data);
-                        self.error = None;
+                ConsoleService::log(&format!("Received update csv: {}", &data));
+                self.input = data.into_bytes();
+                self.file = None;
+                self.sheets.clear();
+                self.sheet = None;
+                self.parse();
+            },
+            Msg::LoadFile(file) => {
+                self.load_file(file);
+            }
+            Msg::FileLoaded(data) => {
+                ConsoleService::log(&format!("Loaded file {}", data.name));
+                self.input = data.content;
+                self.reader_task = None;
+                self.sheets = if is_spreadsheet(&self.input) {
+                    sheet_names(&self.input).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                self.sheet = self.sheets.first().cloned();
+                self.parse();
+            }
+            Msg::DragOver => return false,
+            Msg::SelectSheet(index) => {
+                self.sheet = self.sheets.get(index).cloned();
+                self.parse();
+            }
+            Msg::ToggleMultipack => {
+                ConsoleService::log(&format!("Toggle multipack; current = {}", self.packing.multipack));
+                self.packing.multipack = !self.packing.multipack;
+                self.packing_changed();
+            }
+            Msg::SetRuleKind(i, kind) => {
+                let rule = &mut self.packing.rules[i];
+                rule.condition = rule.condition.of_kind(kind);
+                self.packing_changed();
+            }
+            Msg::SetRuleValue(i, value) => {
+                let rule = &mut self.packing.rules[i];
+                if let Some(condition) = rule.condition.with_value(&value) {
+                    rule.condition = condition;
+                    self.packing_changed();
+                }
+            }
+            Msg::SetRulePackages(i, packages) => {
+                if let Ok(packages) = packages.trim().parse::<u32>() {
+                    self.packing.rules[i].packages = packages.max(1);
+                    self.packing_changed();
+                }
+            }
+            Msg::AddRule => {
+                self.packing.rules.push(PackRule { condition: Condition::Always, packages: 1 });
+                self.packing_changed();
+            }
+            Msg::RemoveRule(i) => {
+                self.packing.rules.remove(i);
+                self.packing_changed();
+            }
+            Msg::MoveRuleUp(i) => {
+                if i > 0 {
+                    self.packing.rules.swap(i - 1, i);
+                    self.packing_changed();
+                }
+            }
+            Msg::ResetRules => {
+                self.packing.rules = PackingOptions::default_rules();
+                self.packing_changed();
+            }
+            Msg::SetMaxWeight(weight) => {
+                if let Some(weight) = catalogue::parse_thousandths(&weight) {
+                    self.packing.max_weight = Some(weight).filter(|w| *w > 0);
+                    self.packing_changed();
+                }
+            }
+            Msg::SetMaxVolume(volume) => {
+                if let Some(volume) = catalogue::parse_thousandths(&volume) {
+                    self.packing.max_volume = Some(volume).filter(|v| *v > 0);
+                    self.packing_changed();
+                }
+            }
+            Msg::ToggleSplitLines => {
+                self.packing.split_lines = !self.packing.split_lines;
+                self.packing_changed();
+            }
+            Msg::SetCatalogue(text) => {
+                match self.catalogue.set_text(&text) {
+                    Ok(()) => {
+                        self.catalogue_error = None;
+                        self.catalogue_changed();
+                        self.reset_scans();
                     }
-                    Err(e) => {
-                        self.error = Some(e.into());
-                        self.input_data = None;
+                    Err(e) => self.catalogue_error = Some(e.to_string()),
+                }
+                self.catalogue_text = text;
+            }
+            Msg::LoadCatalogue(file) => {
+                match self.reader.read_file(file, self.link.callback(Msg::CatalogueLoaded)) {
+                    Ok(task) => self.catalogue_task = Some(task),
+                    Err(e) => self.catalogue_error = Some(e.to_string()),
+                }
+            }
+            Msg::CatalogueLoaded(data) => {
+                self.catalogue_task = None;
+                self.load_catalogue(&data.content);
+            }
+            Msg::SetBundles(text) => {
+                self.catalogue.bundles.set_text(&text);
+                self.bundles_text = text;
+                self.catalogue_changed();
+                self.reset_scans();
+            }
+            Msg::ToggleExpandBundles => {
+                self.catalogue.bundles.expand = !self.catalogue.bundles.expand;
+                self.catalogue_changed();
+                self.reset_scans();
+            }
+            Msg::ToggleBundleBreakdown => {
+                self.catalogue.bundles.breakdown_on_labels = !self.catalogue.bundles.breakdown_on_labels;
+                self.catalogue_changed();
+            }
+            Msg::SortSummary(column) => {
+                self.summary_sort = self.summary_sort.toggle(column);
+                self.summary_changed();
+            }
+            Msg::SetPrintMode(mode) => {
+                self.print_mode = mode;
+            }
+            Msg::SetScanText(text) => {
+                self.scan_text = text;
+            }
+            Msg::ScanPackage => {
+                if !self.scan_text.trim().is_empty() {
+                    self.verification.scan(&self.labels, &self.scan_text);
+                }
+                self.scan_text.clear();
+            }
+            Msg::ResetScans => {
+                self.reset_scans();
+            }
+            Msg::SetLabelLayout(index) => {
+                match LabelSize::ALL.get(index) {
+                    Some(size) => {
+                        self.label_options.size = *size;
+                        self.label_options.sheet = None;
                     }
+                    None => self.label_options.sheet = self.label_options.sheets().get(index - LabelSize::ALL.len()).map(|sheet| sheet.name.clone()),
                 }
-            },
-            Msg::ToggleMultipack => {
-                stdweb::console!(log, "Toggle multipack; current = ", self.multipack);
-                self.multipack = !self.multipack;
+                self.label_options_changed();
+            }
+            Msg::SetSkipLabels(skip) => {
+                self.skip_labels = skip.trim().parse().unwrap_or(0);
+                self.pdf_url = None;
+            }
+            Msg::SetCustomSheets(text) => {
+                self.label_options.set_custom_sheets_text(&text);
+                self.custom_sheets_text = text;
+                self.label_options_changed();
+            }
+            Msg::MakePdf => {
+                if self.input_data.is_some() {
+                    let fonts = self.label_fonts.get_or_insert_with(LabelFonts::load);
+                    self.pdf_url = Some(base64_data_url("application/pdf", &labelpdf::labels_pdf(&self.labels, fonts, &self.label_options, self.skip_labels)));
+                }
+            }
+            Msg::SetPickGroupBy(group_by) => {
+                self.catalogue.pick_list.group_by = group_by;
+                self.catalogue_changed();
+            }
+            Msg::SetWalkPath(text) => {
+                self.catalogue.pick_list.set_walk_path_text(&text);
+                self.walk_path_text = text;
+                self.catalogue_changed();
+            }
+            Msg::SetZoneRules(text) => {
+                self.catalogue.pick_list.set_zone_rules_text(&text);
+                self.zone_rules_text = text;
+                self.catalogue_changed();
+            }
+            Msg::SetNameMappings(text) => {
+                self.catalogue.short_names.set_mappings_text(&text);
+                self.name_mappings_text = text;
+                self.catalogue_changed();
+            }
+            Msg::ToggleDropUnits => {
+                self.catalogue.short_names.drop_units = !self.catalogue.short_names.drop_units;
+                self.catalogue_changed();
+            }
+            Msg::SetDropPhrases(text) => {
+                self.catalogue.short_names.set_drop_phrases_text(&text);
+                self.drop_phrases_text = text;
+                self.catalogue_changed();
+            }
+            Msg::SetMaxNameLength(length) => {
+                self.catalogue.short_names.max_length = length.trim().parse().unwrap_or(0);
+                self.catalogue_changed();
+            }
+            Msg::SetTemperatureRules(text) => {
+                self.catalogue.set_temperature_rules_text(&text);
+                self.temperature_rules_text = text;
+                self.catalogue_changed();
+                self.reset_scans();
+            }
+            Msg::SelectProfile(index) => {
+                self.profiles.select(index);
+                self.profiles_changed();
+            }
+            Msg::SetProfileHeader(field, header) => {
+                self.profiles.selected_mut().set_header(field, header);
+                self.profiles_changed();
+            }
+            Msg::UpdateNewProfileName(name) => {
+                self.new_profile_name = name;
+            }
+            Msg::AddProfile => {
+                let name = self.new_profile_name.trim().to_owned();
+                if !name.is_empty() {
+                    self.profiles.add(&name);
+                    self.new_profile_name.clear();
+                    self.profiles_changed();
+                }
+            }
+            Msg::RemoveProfile => {
+                self.profiles.remove_selected();
+                self.profiles_changed();
+            }
+            Msg::SetDefaultVat(vat) => {
+                self.reconcile.default_vat = vat.trim().parse().unwrap_or(0);
+                persist::save(RECONCILE_KEY, &self.reconcile);
+            }
+            Msg::SetTolerance(tolerance) => {
+                if let Ok(tolerance) = Money::parse(&tolerance) {
+                    self.reconcile.tolerance = tolerance.abs();
+                    persist::save(RECONCILE_KEY, &self.reconcile);
+                }
+            }
+            Msg::SetVatRules(text) => {
+                self.reconcile.set_vat_rules_text(&text);
+                self.vat_rules_text = text;
+                persist::save(RECONCILE_KEY, &self.reconcile);
             }
         };
         true
@@ -54,53 +1173,81 @@ impl Component for Gui {
     fn view(&self) -> Html {
         use yew::InputData;
         let empty = html! {<div/>};
+        let labels = &self.labels;
         html! {
             <div width="100%">
                 <div class="input-area">
-                    <div>{"Copy-paste your woocommerce CSV into the textarea below:"}</div>
+                    { self.profiles_view() }
+                    { self.file_view() }
+                    <div>{"Copy-paste your woocommerce CSV, or the JSON of the orders API, into the textarea below:"}</div>
                     <textarea
                         rows="30" cols="120"
                         oninput=self.link.callback(|e: InputData| Msg::UpdateCsv(e.value))
                     />
-                    <input type="checkbox" id="checkbox-multipack" checked={self.multipack} onclick=self.link.callback(|_| Msg::ToggleMultipack)/>
+                    <input type="checkbox" id="checkbox-multipack" checked={self.packing.multipack} onclick=self.link.callback(|_| Msg::ToggleMultipack)/>
                     <label for="checkbox-multipack">{"Multi-pack"}</label>
-                    <h2>{"Labels"}</h2>
+                    { self.packing_view() }
+                    { self.catalogue_view() }
+                    { self.short_names_view() }
+                    { self.bundles_view() }
+                    { self.pick_list_options_view() }
+                    {
+                        self.input_data.as_ref().and_then(|d| d.dialect.as_ref()).map(|dialect| html!{
+                            <div class="dialect">{format!("Detected format: {}", dialect)}</div>
+                        }).unwrap_or(empty.clone())
+                    }
+                    {
+                        self.input_data.as_ref()
+                            .map(|d| Self::diagnostics_view(&d.diagnostics))
+                            .unwrap_or(empty.clone())
+                    }
+                    { self.reconcile_view(labels) }
+                    { self.print_mode_view() }
+                    { if self.print_mode == PrintMode::Labels { self.pdf_view() } else { empty.clone() } }
+                    { if self.print_mode == PrintMode::Labels && !labels.is_empty() { thermal_view(&self.zpl_url, &self.epl_url) } else { empty.clone() } }
                 </div>
                 {
-                    self.input_data.as_ref().map(|d| html!{
+                    if self.print_mode == PrintMode::PackingSlips {
+                        labels.iter().map(packing_slip_view).collect::<Html>()
+                    } else {
+                        empty.clone()
+                    }
+                }
+                {
+                    if self.print_mode == PrintMode::Manifest {
+                        manifest_view(&self.manifest, &self.manifest_url)
+                    } else {
+                        empty.clone()
+                    }
+                }
+                {
+                    if self.print_mode == PrintMode::Scanning {
+                        self.scanning_view(labels)
+                    } else {
+                        empty.clone()
+                    }
+                }
+                {
+                    self.input_data.as_ref().filter(|_| self.print_mode == PrintMode::PickList).map(|d| html!{
                     <div>
+                        <h2>{"Lista di prelievo"}</h2>
+                        { self.pick_list_view(d) }
+                    </div>
+                    }).unwrap_or(empty.clone())
+                }
+                {
+                    self.input_data.as_ref().filter(|_| self.print_mode == PrintMode::Labels).map(|_| html!{
+                    <div>
+                        <div>
                         {
-                            d.labels(self.multipack).map(|labels| html!{
-                                <div>
-                                {
-                                    labels.iter().map(|label| label.view()).collect::<Html>()
-                                }
-                                <hr/>
-                                <p>{format!("Number of deliveries: {}", labels.len())}</p>
-                                </div>
-                            }).unwrap_or_else(|e| {
-                                html! {
-                                    <div class="error">{e.to_string()}</div>
-                                }
-                            })
+                            labels.iter().map(|label| label.view()).collect::<Html>()
                         }
                         <hr/>
+                        <p>{format!("Number of deliveries: {}", labels.len())}</p>
+                        </div>
+                        <hr/>
                         <h2>{"Summary"}</h2>
-                        <table>
-                            <thead>
-                                <tr>
-                                    <th class="product" align="left">{"Prodotto"}</th>
-                                    <th class="quantity" align="right">{"Quantità"}</th>
-                                </tr>
-                            </thead>
-                            <tbody> {
-                                d.summary().iter().map(|(prod, qty)| html! {
-                                    <tr>
-                                        <td>{&prod}</td> <td align="right">{format!("{}", qty)}</td>
-                                    </tr>
-                                }).collect::<Html>()
-                            } </tbody>
-                        </table>
+                        { self.summary_view() }
                     </div>
                     }).unwrap_or(empty.clone())
                 }
@@ -119,7 +1266,7 @@ impl Component for InputData {
     type Properties = ();
 
     fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
-        InputData { data: Vec::new() }
+        InputData { data: Vec::new(), dialect: None, diagnostics: Vec::new() }
     }
 
     fn update(&mut self, _msg: Self::Message) -> bool {
@@ -245,9 +1392,16 @@ impl Component for OrderDetails {
         {
             self.packages.iter().enumerate().map(|(i, products)| { html! {
                 <div class="the-label">
+                    {
+                        self.package_temperature(i).map(|temperature| html! {
+                            <div class=format!("temperature temperature-{}", temperature)>
+                                {temperature.banner()}
+                            </div>
+                        }).unwrap_or_default()
+                    }
                     <table class="address" width="100%">
                         <tr>
-                            <td width="60%" valign="top"></td>
+                            <td width="60%" valign="top">{package_code_view(self.package_code(i))}</td>
                             <td>
                                 <b>{format!("Collo {} di {}", i+1, self.packages.len())}</b>
                             </td>
@@ -280,6 +1434,66 @@ impl Component for OrderDetails {
     }
 }
 
+/// The barcodes of a package, as inline SVG: Code 128 with the code printed below, and a QR code.
+fn package_code_view(code: PackageCode) -> Html {
+    let text = code.to_string();
+    let svg = |svg: Svg, class: &str| html! {
+        <svg class=class viewBox=format!("0 0 {} {}", svg.width, svg.height) shape-rendering="crispEdges">
+            <rect width=svg.width.to_string() height=svg.height.to_string() fill="#fff"/>
+            <path d=svg.path fill="#000"/>
+        </svg>
+    };
+    html! {
+        <div class="package-code">
+            <div class="code128">
+                {barcode::code128(&text).map_or(html! {}, |modules| svg(Svg::code128(&modules, 40), "code128"))}
+                <div>{&text}</div>
+            </div>
+            {QrCode::encode(&text).map_or(html! {}, |qr| svg(Svg::qr(&qr), "qr"))}
+        </div>
+    }
+}
+
+/// A `data:` URL holding `text`, for links that download it as a file.
+fn data_url(mime: &str, text: &str) -> String {
+    let mut url = format!("data:{};charset=utf-8,", mime);
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// A `data:` URL holding `bytes` in base 64, for links that download binary files.
+fn base64_data_url(mime: &str, bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut url = format!("data:{};base64,", mime);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                url.push(DIGITS[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                url.push('=');
+            }
+        }
+    }
+    url
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} kB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
 fn address_view(order: &OrderDetails) -> Html {
     html!{
         <table class="address" width="100%">
@@ -302,6 +1516,110 @@ fn address_view(order: &OrderDetails) -> Html {
     }
 }
 
+/// A page for the packer of `order`: every product of all its packages with a box to tick, and
+/// room for their initials and for checking the number of packages.
+fn packing_slip_view(order: &OrderDetails) -> Html {
+    html! {
+        <div class="packing-slip">
+            <h2>{format!("Distinta di preparazione - Ordine N. {}", order.order_id)}</h2>
+            { address_view(order) }
+            <table class="order-items" width="100%">
+                <thead>
+                    <tr>
+                        <th></th>
+                        <th class="quantity">{"Quantità"}</th>
+                        <th class="product">{"Prodotto"}</th>
+                        <th>{"Collo"}</th>
+                    </tr>
+                </thead>
+                <tbody> {
+                    order.slip_lines().iter().map(|line| html! {
+                        <tr>
+                            <td class="tick"><input type="checkbox"/></td>
+                            <td class="quantity" align="center">{line.quantity}</td>
+                            <td class="product">
+                                {&line.product_name}
+                                {
+                                    if line.components.is_empty() {
+                                        html! {}
+                                    } else {
+                                        html! {
+                                            <ul class="components"> {
+                                                line.components.iter().map(|c| html! { <li>{format!("{} {}", c.quantity, c.product_name)}</li> }).collect::<Html>()
+                                            } </ul>
+                                        }
+                                    }
+                                }
+                            </td>
+                            <td align="center">{line.packages.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")}</td>
+                        </tr>
+                    }).collect::<Html>()
+                } </tbody>
+            </table>
+            <table class="slip-confirmation" width="100%">
+                <tr>
+                    <td><input type="checkbox"/>{format!(" Colli preparati: ____ di {}", order.packages.len())}</td>
+                    <td align="right">{"Sigla preparatore: ____________"}</td>
+                </tr>
+            </table>
+        </div>
+    }
+}
+
+/// Links to the labels for a Zebra thermal printer, 4 × 6 inches, to send as they are.
+fn thermal_view(zpl_url: &str, epl_url: &str) -> Html {
+    html! {
+        <div class="thermal">
+            <span>{"Thermal printer (4 × 6 in, 203 dpi): "}</span>
+            <a href=zpl_url download="etichette.zpl">{"etichette.zpl"}</a>
+            <span>{" or, for EPL printers, "}</span>
+            <a href=epl_url download="etichette.epl">{"etichette.epl"}</a>
+        </div>
+    }
+}
+
+/// The delivery sheet for the driver, with a column for the signatures of the customers.
+fn manifest_view(rows: &[ManifestRow], csv_url: &str) -> Html {
+    html! {
+        <div class="manifest">
+            <a class="export" href=csv_url download="distinta.csv">{"Export as CSV"}</a>
+            <h2>{"Distinta di consegna"}</h2>
+            <table>
+                <thead>
+                    <tr>
+                        <th align="left">{"Ordine"}</th>
+                        <th align="left">{"Cliente"}</th>
+                        <th align="left">{"Indirizzo"}</th>
+                        <th align="left">{"CAP"}</th>
+                        <th align="left">{"Telefono"}</th>
+                        <th align="right">{"Colli"}</th>
+                        <th align="left">{"Pagamento"}</th>
+                        <th align="right">{"Da incassare"}</th>
+                        <th class="signature">{"Firma"}</th>
+                    </tr>
+                </thead>
+                <tbody> {
+                    rows.iter().map(|row| html! {
+                        <tr>
+                            <td>{row.order_id}</td>
+                            <td>{&row.customer_name}</td>
+                            <td>{&row.address}</td>
+                            <td>{&row.postcode}</td>
+                            <td>{&row.phone}</td>
+                            <td align="right">{row.packages}</td>
+                            <td>{&row.payment}</td>
+                            <td align="right">{if row.to_collect == Money::ZERO { String::new() } else { format!("{} €", row.to_collect) }}</td>
+                            <td class="signature"></td>
+                        </tr>
+                    }).collect::<Html>()
+                } </tbody>
+            </table>
+            <p>{format!("Consegne: {}, colli: {}, da incassare: {} €",
+                rows.len(), rows.iter().map(|row| row.packages).sum::<usize>(), rows.iter().map(|row| row.to_collect).sum::<Money>())}</p>
+        </div>
+    }
+}
+
 impl Properties for OrderItem {
     type Builder = OrderItemBuilder;
 
@@ -329,8 +1647,29 @@ impl Component for OrderItem {
     fn view(&self) -> Html {
         html! {
             <tr>
-                <td class="quantity" align="center">{self.quantity}</td>
-                <td class="product"><b>{&self.product_name}</b></td>
+                <td class="quantity" align="center">{
+                    if self.ordered > self.quantity {
+                        format!("{} di {}", self.quantity, self.ordered)
+                    } else {
+                        self.quantity.to_string()
+                    }
+                }</td>
+                <td class="product">
+                    <b>{&self.label_name}</b>
+                    {
+                        if self.components.is_empty() {
+                            html! {}
+                        } else {
+                            html! {
+                                <ul class="components"> {
+                                    self.components.iter().map(|c| html! {
+                                        <li>{format!("{} × {}", c.quantity, c.product_name)}</li>
+                                    }).collect::<Html>()
+                                } </ul>
+                            }
+                        }
+                    }
+                </td>
             </tr>
         }
     }
@@ -0,0 +1,430 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use anyhow::anyhow;
+
+/// What the barcodes of a package say: the order, and which of its packages it is, as
+/// `ORDER-PACKAGE/PACKAGES` in decimal digits, e.g. `5358-2/3` for the second of the three
+/// packages of order 5358. Packages count from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageCode {
+    pub order_id: u32,
+    pub package: usize,
+    pub packages: usize,
+}
+
+impl PackageCode {
+    /// The code of `text`, e.g. as typed by a barcode scanner, or `None` if it is not one.
+    pub fn decode(text: &str) -> Option<PackageCode> {
+        let (order_id, rest) = text.trim().split_once('-')?;
+        let (package, packages) = rest.split_once('/')?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !(digits(order_id) && digits(package) && digits(packages)) {
+            return None;
+        }
+        let code = PackageCode { order_id: order_id.parse().ok()?, package: package.parse().ok()?, packages: packages.parse().ok()? };
+        Some(code).filter(|code| code.package >= 1 && code.package <= code.packages)
+    }
+}
+
+impl FromStr for PackageCode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PackageCode::decode(s).ok_or_else(|| anyhow!("{:?} is not the code of a package", s))
+    }
+}
+
+impl Display for PackageCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}/{}", self.order_id, self.package, self.packages)
+    }
+}
+
+/// The widths of the bars and spaces of the symbols of Code 128, from value 0 to the start
+/// symbols (103 to 105) and the stop symbol (106).
+const CODE128: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213",
+    "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132",
+    "221231", "213212", "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211",
+    "212123", "212321", "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
+    "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121", "313121", "211331",
+    "231131", "213113", "213311", "213131", "311123", "311321", "331121", "312113", "312311", "332111",
+    "314111", "221411", "431111", "111224", "111422", "121124", "121421", "141122", "141221", "112214",
+    "112412", "122114", "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
+    "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311", "113141",
+    "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const START_B: usize = 104;
+const STOP: usize = 106;
+
+/// `text` as Code 128 (code set B), one `true` for each dark module, without quiet zones; `None`
+/// if `text` has characters outside printable ASCII.
+pub fn code128(text: &str) -> Option<Vec<bool>> {
+    let values = text.bytes()
+        .map(|b| if (32..127).contains(&b) { Some(usize::from(b - 32)) } else { None })
+        .collect::<Option<Vec<_>>>()?;
+    let checksum = values.iter().enumerate().fold(START_B, |sum, (i, value)| sum + (i + 1) * value) % 103;
+    let mut modules = Vec::new();
+    for symbol in std::iter::once(START_B).chain(values).chain(vec![checksum, STOP]) {
+        for (i, width) in CODE128[symbol].bytes().enumerate() {
+            modules.resize(modules.len() + usize::from(width - b'0'), i % 2 == 0);
+        }
+    }
+    Some(modules)
+}
+
+/// A QR code, at error correction level M.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+    /// Finder, timing and alignment patterns and format information, which masks leave alone.
+    function: Vec<bool>,
+}
+
+/// Data and error correction codewords of QR versions 1 to 3 at level M, which have a single
+/// block each.
+const QR_CODEWORDS: [(usize, usize); 3] = [(16, 10), (28, 16), (44, 26)];
+
+impl QrCode {
+    /// `text` as a QR code in byte mode, of the smallest version that holds it, or `None` if it
+    /// holds more than 42 bytes (version 3).
+    pub fn encode(text: &str) -> Option<QrCode> {
+        let bytes = text.as_bytes();
+        let version = QR_CODEWORDS.iter().position(|(data, _)| bytes.len() + 2 <= *data)? + 1;
+        let (data_codewords, ec_codewords) = QR_CODEWORDS[version - 1];
+
+        let mut bits = Vec::new();
+        let mut push = |value: usize, length: usize| (0..length).rev().for_each(|i| bits.push(value >> i & 1 == 1));
+        push(0b0100, 4);
+        push(bytes.len(), 8);
+        bytes.iter().for_each(|&b| push(usize::from(b), 8));
+        let capacity = data_codewords * 8;
+        let terminator = (capacity - bits.len()).min(4);
+        bits.resize(bits.len() + terminator, false);
+        bits.resize(bits.len() + (8 - bits.len() % 8) % 8, false);
+        let mut data = bits.chunks(8).map(|byte| byte.iter().fold(0u8, |b, &bit| b << 1 | u8::from(bit))).collect::<Vec<_>>();
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if data.len() == data_codewords {
+                break;
+            }
+            data.push(*pad);
+        }
+        let ec = reed_solomon(&data, ec_codewords);
+        data.extend(ec);
+
+        let size = 17 + 4 * version;
+        let mut qr = QrCode { size, modules: vec![false; size * size], function: vec![false; size * size] };
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&data);
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut candidate = qr.clone();
+                candidate.apply_mask(mask);
+                candidate.draw_format(mask);
+                candidate.penalty()
+            })
+            .expect("eight masks");
+        qr.apply_mask(mask);
+        qr.draw_format(mask);
+        Some(qr)
+    }
+
+    /// Modules on a side, without the quiet zone.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module in column `x` and row `y` is dark.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set(6, i, i % 2 == 0);
+            self.set(i, 6, i % 2 == 0);
+        }
+        for &(x, y) in &[(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (mx, my) = (x as i32 + dx, y as i32 + dy);
+                    if mx >= 0 && my >= 0 && (mx as usize) < size && (my as usize) < size {
+                        let distance = dx.abs().max(dy.abs());
+                        self.set(mx as usize, my as usize, distance != 2 && distance != 4);
+                    }
+                }
+            }
+        }
+        if version > 1 {
+            let c = size - 7;
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    self.set((c as i32 + dx) as usize, (c as i32 + dy) as usize, dx.abs().max(dy.abs()) != 1);
+                }
+            }
+        }
+        // Reserve the format areas, drawn after masking.
+        self.draw_format(0);
+    }
+
+    /// Draws the two copies of the format information, for level M and `mask`.
+    fn draw_format(&mut self, mask: usize) {
+        let data = mask; // level M is 00
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| bits >> i & 1 == 1;
+        let size = self.size;
+        for i in 0..=5 {
+            self.set(8, i, bit(i));
+        }
+        self.set(8, 7, bit(6));
+        self.set(8, 8, bit(7));
+        self.set(7, 8, bit(8));
+        for i in 9..15 {
+            self.set(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set(8, size - 15 + i, bit(i));
+        }
+        self.set(8, size - 8, true);
+    }
+
+    /// Places the codewords in two-module columns, zigzagging up and down from the bottom right.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vertical } else { vertical };
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = codewords[i / 8] >> (7 - i % 8) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: usize) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.function[y * self.size + x] {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// How hard the code is to scan, by the rules of the standard for choosing the mask: long
+    /// runs, 2×2 blocks, patterns like the finders, and too much dark or light.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines = (0..size).flat_map(|i| vec![
+            (0..size).map(|j| self.get(j, i)).collect::<Vec<_>>(),
+            (0..size).map(|j| self.get(i, j)).collect::<Vec<_>>(),
+        ]);
+        for line in lines {
+            let mut run = 1;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            let finder = [true, false, true, true, true, false, true];
+            for j in 0..size.saturating_sub(6) {
+                if line[j..j + 7] == finder {
+                    let light_before = j >= 4 && line[j - 4..j].iter().all(|dark| !dark);
+                    let light_after = j + 11 <= size && line[j + 7..j + 11].iter().all(|dark| !dark);
+                    if light_before || light_after {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if self.get(x + 1, y) == dark && self.get(x, y + 1) == dark && self.get(x + 1, y + 1) == dark {
+                    penalty += 3;
+                }
+            }
+        }
+        let total = size * size;
+        let dark = self.modules.iter().filter(|dark| **dark).count();
+        let deviation = (dark * 20).max(total * 10) - (dark * 20).min(total * 10);
+        let steps = deviation / total + usize::from(deviation % total > 0);
+        penalty + steps * 10 - 10
+    }
+}
+
+/// Multiplication in GF(2⁸), modulo the polynomial of QR codes.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= u16::from(y >> i & 1) * u16::from(x);
+    }
+    z as u8
+}
+
+/// The `degree` Reed-Solomon error correction codewords of `data`.
+fn reed_solomon(data: &[u8], degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0u8; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_multiply(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_multiply(root, 2);
+    }
+    let mut remainder = vec![0u8; degree];
+    for &b in data {
+        let factor = b ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, d) in remainder.iter_mut().zip(&divisor) {
+            *r ^= gf_multiply(*d, factor);
+        }
+    }
+    remainder
+}
+
+/// A barcode for an SVG image: the size of its view box, in modules and with quiet zones, and the
+/// path of its dark modules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Svg {
+    pub width: usize,
+    pub height: usize,
+    pub path: String,
+}
+
+impl Svg {
+    /// Code 128, `height` modules tall, with quiet zones of ten modules.
+    pub fn code128(modules: &[bool], height: usize) -> Svg {
+        let mut path = String::new();
+        for (x, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+            path.push_str(&format!("M{} 0h1v{}h-1z", x + 10, height));
+        }
+        Svg { width: modules.len() + 20, height, path }
+    }
+
+    /// A QR code, with a quiet zone of four modules.
+    pub fn qr(qr: &QrCode) -> Svg {
+        let mut path = String::new();
+        for y in 0..qr.size() {
+            for x in (0..qr.size()).filter(|&x| qr.get(x, y)) {
+                path.push_str(&format!("M{} {}h1v1h-1z", x + 4, y + 4));
+            }
+        }
+        Svg { width: qr.size() + 8, height: qr.size() + 8, path }
+    }
+}
+
+#[test]
+fn test_package_code() {
+    let code = PackageCode { order_id: 5358, package: 2, packages: 3 };
+    assert_eq!(code.to_string(), "5358-2/3");
+    assert_eq!(PackageCode::decode(" 5358-2/3\n"), Some(code));
+    assert_eq!("5358-2/3".parse::<PackageCode>().unwrap(), code);
+    for text in &["5358-4/3", "5358-0/3", "5358/2-3", "5358-2", "A5358-2/3", "5358-+2/3", ""] {
+        assert_eq!(PackageCode::decode(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn test_code128() {
+    assert!(CODE128[..106].iter().all(|symbol| symbol.bytes().map(|b| b - b'0').sum::<u8>() == 11));
+    assert_eq!(CODE128.iter().collect::<std::collections::HashSet<_>>().len(), 107);
+    // Start B, "5", "3", "5", "8", "-", "2", "/", "3", check, stop.
+    let modules = code128("5358-2/3").unwrap();
+    assert_eq!(modules.len(), 11 * 10 + 13);
+    let checksum = (104 + 21 + 2 * 19 + 3 * 21 + 4 * 24 + 5 * 13 + 6 * 18 + 7 * 15 + 8 * 19) % 103;
+    let symbol = modules[99..110].iter().map(|dark| if *dark { '1' } else { '0' }).collect::<String>();
+    let expected = CODE128[checksum].bytes().enumerate()
+        .flat_map(|(i, width)| vec![if i % 2 == 0 { '1' } else { '0' }; usize::from(width - b'0')])
+        .collect::<String>();
+    assert_eq!(symbol, expected);
+    assert_eq!(code128("città"), None);
+}
+
+#[test]
+fn test_reed_solomon() {
+    // The example of version 1-M in the tutorial at thonky.com: HELLO WORLD.
+    let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+    assert_eq!(reed_solomon(&data, 10), vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+}
+
+#[test]
+fn test_qr_code() {
+    let qr = QrCode::encode("5358-2/3").unwrap();
+    assert_eq!(qr.size(), 21);
+    // Finder patterns in three corners, and the dark module.
+    for &(x, y) in &[(0, 0), (20, 0), (0, 20)] {
+        assert!(qr.get(x, y));
+    }
+    assert!(qr.get(8, 13));
+    // The format information of level M and the chosen mask, read back from the first copy.
+    let bits = (0..=5).map(|i| qr.get(8, i)).chain(vec![qr.get(8, 7), qr.get(8, 8), qr.get(7, 8)])
+        .chain((9..15).map(|i| qr.get(14 - i, 8)))
+        .enumerate()
+        .fold(0, |bits, (i, dark)| bits | usize::from(dark) << i);
+    let format = |mask: usize| {
+        let mut remainder = mask;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        (mask << 10 | remainder) ^ 0x5412
+    };
+    assert_eq!(format(0), 0b101010000010010);
+    assert!((0..8).any(|mask| format(mask) == bits));
+    assert_eq!(QrCode::encode(&"x".repeat(26)).unwrap().size(), 25);
+    assert_eq!(QrCode::encode(&"x".repeat(42)).unwrap().size(), 29);
+    assert_eq!(QrCode::encode(&"x".repeat(43)), None);
+    let svg = Svg::qr(&qr);
+    assert_eq!((svg.width, svg.height), (29, 29));
+    assert!(svg.path.starts_with("M4 4h1v1h-1z"));
+}
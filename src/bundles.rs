@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// A product of a bundle, and how many units of it one bundle holds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundleComponent {
+    pub product_name: String,
+    pub quantity: u32,
+}
+
+/// A product sold as a box of other products, e.g. `SELEZIONE B "IL VEGETARIANO"`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// The name in exports, compared ignoring case.
+    pub name: String,
+    pub components: Vec<BundleComponent>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bundles {
+    pub bundles: Vec<Bundle>,
+    /// Whether the summary and the pick list count the components of bundles instead of the
+    /// bundles.
+    pub expand: bool,
+    /// Whether labels list the components under the name of each bundle.
+    pub breakdown_on_labels: bool,
+}
+
+impl Bundles {
+    pub fn find(&self, product_name: &str) -> Option<&Bundle> {
+        let name = product_name.trim().to_lowercase();
+        self.bundles.iter().find(|bundle| bundle.name.trim().to_lowercase() == name)
+    }
+
+    /// The components of `quantity` units of the bundle called `product_name`, or the product
+    /// itself if it is not a bundle.
+    pub fn expand(&self, product_name: &str, quantity: u32) -> Vec<BundleComponent> {
+        match self.find(product_name) {
+            Some(bundle) => bundle.components.iter()
+                .map(|c| BundleComponent { product_name: c.product_name.clone(), quantity: c.quantity * quantity })
+                .collect(),
+            None => vec![BundleComponent { product_name: product_name.to_owned(), quantity }],
+        }
+    }
+
+    /// The bundles as text, for editing: the name of each bundle on a line of its own, followed
+    /// by its components, one `quantity NAME` per line, indented.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for bundle in &self.bundles {
+            text.push_str(&bundle.name);
+            text.push('\n');
+            for component in &bundle.components {
+                text.push_str(&format!("    {} {}\n", component.quantity, component.product_name));
+            }
+        }
+        text
+    }
+
+    /// Replaces the bundles with the ones in `text`, in the format of `text`. A component line
+    /// without a quantity counts one unit; bundles without components are left out.
+    pub fn set_text(&mut self, text: &str) {
+        let mut bundles: Vec<Bundle> = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let indented = line.starts_with(char::is_whitespace);
+            match bundles.last_mut() {
+                Some(bundle) if indented => {
+                    let line = line.trim();
+                    let (quantity, name) = line.split_once(char::is_whitespace)
+                        .and_then(|(quantity, name)| Some((quantity.trim_end_matches(&['x', 'X'][..]).parse().ok()?, name.trim())))
+                        .unwrap_or((1, line));
+                    bundle.components.push(BundleComponent { product_name: name.to_owned(), quantity });
+                }
+                _ => bundles.push(Bundle { name: line.trim().trim_end_matches(':').trim().to_owned(), components: Vec::new() }),
+            }
+        }
+        bundles.retain(|bundle| !bundle.components.is_empty());
+        self.bundles = bundles;
+    }
+}
+
+#[cfg(test)]
+const BUNDLES: &str = "\
+SELEZIONE B \"IL VEGETARIANO\":
+    2 ZUCCHINE 500 g
+    3x UOVA
+    PANE AI CEREALI ANTICHI 500 g
+
+EMPTY BOX
+";
+
+#[test]
+fn test_bundles_text() {
+    let mut bundles = Bundles::default();
+    bundles.set_text(BUNDLES);
+    assert_eq!(bundles.bundles, vec![Bundle {
+        name: "SELEZIONE B \"IL VEGETARIANO\"".to_owned(),
+        components: vec![
+            BundleComponent { product_name: "ZUCCHINE 500 g".to_owned(), quantity: 2 },
+            BundleComponent { product_name: "UOVA".to_owned(), quantity: 3 },
+            BundleComponent { product_name: "PANE AI CEREALI ANTICHI 500 g".to_owned(), quantity: 1 },
+        ],
+    }]);
+    let mut copy = Bundles::default();
+    copy.set_text(&bundles.text());
+    assert_eq!(copy, bundles);
+}
+
+#[test]
+fn test_expand() {
+    let mut bundles = Bundles::default();
+    bundles.set_text(BUNDLES);
+    let expanded = bundles.expand("selezione b \"il vegetariano\"", 2);
+    assert_eq!(expanded.iter().map(|c| c.quantity).collect::<Vec<_>>(), vec![4, 6, 2]);
+    assert_eq!(bundles.expand("UOVA", 5), vec![BundleComponent { product_name: "UOVA".to_owned(), quantity: 5 }]);
+}
@@ -0,0 +1,591 @@
+use crate::bundles::Bundles;
+use crate::columns::normalize;
+use crate::dialect::{self, Dialect};
+use crate::picklist::PickListOptions;
+use crate::shortnames::ShortNames;
+use crate::wooapi::is_json;
+use crate::woocsv::WooCommerceRow;
+use anyhow::{anyhow, Result};
+use csv::ReaderBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+pub const CATALOGUE_KEY: &str = "campagnamica.catalogue";
+
+/// Names in exports that are at least this similar to the name of a product (see `similarity`)
+/// are taken to be that product.
+const FUZZY_THRESHOLD: f64 = 0.75;
+
+/// What we know about a product, matched to the rows of an export by SKU or by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Product {
+    pub sku: String,
+    /// The canonical name, which the names in exports are matched to.
+    pub name: String,
+    /// The name printed on labels; the canonical name if empty.
+    pub short_name: String,
+    pub category: String,
+    pub producer: String,
+    /// Grams per unit; zero if unknown.
+    pub weight: u32,
+    /// Millilitres per unit; zero if unknown.
+    pub volume: u32,
+    /// If `None`, the temperature rules of the catalogue apply.
+    pub temperature: Option<Temperature>,
+    /// Where the product is kept, e.g. `A3`.
+    pub location: String,
+}
+
+/// A product in a JSON catalogue. The fields are the ones of `Product`, but read like the
+/// columns of a CSV catalogue: the weight in kilograms and the volume in litres, as numbers or as
+/// text with `,` or `.`, and the temperature by any of the names `Temperature::parse` accepts.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct JsonProduct {
+    sku: String,
+    name: String,
+    short_name: String,
+    category: String,
+    producer: String,
+    weight: serde_json::Value,
+    volume: serde_json::Value,
+    temperature: String,
+    location: String,
+}
+
+impl JsonProduct {
+    /// The product, or an error naming its position (from 1) in the array.
+    fn into_product(self, position: usize) -> Result<Product> {
+        let thousandths = |field, value: &serde_json::Value| {
+            match value {
+                serde_json::Value::Null => Some(0),
+                serde_json::Value::Number(n) => parse_thousandths(&n.to_string()),
+                serde_json::Value::String(s) => parse_thousandths(s),
+                _ => None,
+            }
+            .ok_or_else(|| anyhow!("Product {}: invalid {} {}", position, field, value))
+        };
+        let temperature = match self.temperature.trim() {
+            "" => None,
+            t => Some(Temperature::parse(t)
+                .ok_or_else(|| anyhow!("Product {}: unknown temperature {:?}", position, t))?),
+        };
+        Ok(Product {
+            weight: thousandths("weight", &self.weight)?,
+            volume: thousandths("volume", &self.volume)?,
+            temperature,
+            sku: self.sku.trim().to_owned(),
+            name: self.name.trim().to_owned(),
+            short_name: self.short_name.trim().to_owned(),
+            category: self.category.trim().to_owned(),
+            producer: self.producer.trim().to_owned(),
+            location: self.location.trim().to_owned(),
+        })
+    }
+}
+
+/// How a product must be kept during delivery. Products of different classes never share a
+/// package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Temperature {
+    Frozen,
+    Chilled,
+    Ambient,
+}
+
+impl Temperature {
+    pub fn parse(s: &str) -> Option<Temperature> {
+        match s.trim().to_lowercase().as_str() {
+            "frozen" | "surgelato" | "congelato" => Some(Temperature::Frozen),
+            "chilled" | "refrigerato" | "fresco" => Some(Temperature::Chilled),
+            "ambient" | "ambiente" | "secco" => Some(Temperature::Ambient),
+            _ => None,
+        }
+    }
+
+    /// The text of the banner printed on the labels of packages of this class.
+    pub fn banner(self) -> &'static str {
+        match self {
+            Temperature::Frozen => "SURGELATO - conservare a -18 °C",
+            Temperature::Chilled => "REFRIGERATO - conservare tra 0 e 4 °C",
+            Temperature::Ambient => "TEMPERATURA AMBIENTE",
+        }
+    }
+}
+
+impl Display for Temperature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Temperature::Frozen => "frozen",
+            Temperature::Chilled => "chilled",
+            Temperature::Ambient => "ambient",
+        })
+    }
+}
+
+/// The temperature class of the products not in the catalogue whose name contains `keyword`
+/// (case-insensitive).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureRule {
+    pub keyword: String,
+    pub temperature: Temperature,
+}
+
+/// A column of the CSV form of the catalogue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Column {
+    Sku,
+    Name,
+    ShortName,
+    Category,
+    Producer,
+    Weight,
+    Volume,
+    Temperature,
+    Location,
+}
+
+impl Column {
+    const ALL: [Column; 9] = [
+        Column::Sku,
+        Column::Name,
+        Column::ShortName,
+        Column::Category,
+        Column::Producer,
+        Column::Weight,
+        Column::Volume,
+        Column::Temperature,
+        Column::Location,
+    ];
+
+    /// Header names known to map to this column, in English and Italian; the first one is the
+    /// one written by `Catalogue::text`.
+    fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Column::Sku => &["SKU", "Codice", "Code"],
+            Column::Name => &["Name", "Product Name", "Nome", "Prodotto", "Nome Prodotto"],
+            Column::ShortName => &["Short Name", "Label Name", "Nome Breve", "Nome Etichetta"],
+            Column::Category => &["Category", "Categoria"],
+            Column::Producer => &["Producer", "Supplier", "Produttore", "Fornitore"],
+            Column::Weight => &["Weight (kg)", "Weight", "Peso (kg)", "Peso"],
+            Column::Volume => &["Volume (l)", "Volume"],
+            Column::Temperature => &["Temperature", "Temperatura"],
+            Column::Location => &["Location", "Shelf", "Ubicazione", "Scaffale", "Posizione"],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalogue {
+    pub products: Vec<Product>,
+    #[serde(default)]
+    pub temperature_rules: Vec<TemperatureRule>,
+    #[serde(default)]
+    pub short_names: ShortNames,
+    #[serde(default)]
+    pub bundles: Bundles,
+    #[serde(default)]
+    pub pick_list: PickListOptions,
+}
+
+impl Catalogue {
+    /// The product with SKU `sku`, or else the one called `product_name`, or else the one with
+    /// the most similar name, if similar enough. Names are compared ignoring case, accents and
+    /// punctuation, and similar names must have the same numbers, so that `500 g` is never
+    /// taken for `1 kg`. `names` are the normalized names of the products, see `names`.
+    fn find<'a>(&'a self, names: &[String], sku: &str, product_name: &str) -> Option<Match<'a>> {
+        let sku = sku.trim();
+        if !sku.is_empty() {
+            if let Some(product) = self.products.iter().find(|product| product.sku.trim().eq_ignore_ascii_case(sku)) {
+                return Some(Match { product, fuzzy: false });
+            }
+        }
+        let name = normalize_name(product_name);
+        if let Some(i) = names.iter().position(|n| *n == name) {
+            return Some(Match { product: &self.products[i], fuzzy: false });
+        }
+        names.iter()
+            .enumerate()
+            .filter(|(_, n)| numbers(n) == numbers(&name))
+            .map(|(i, n)| (i, similarity(n, &name)))
+            .filter(|(_, score)| *score >= FUZZY_THRESHOLD)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| Match { product: &self.products[i], fuzzy: true })
+    }
+
+    /// Matches the products of `rows` to the catalogue, once for each distinct SKU and name.
+    pub fn index<'a, 'r>(&'a self, rows: impl IntoIterator<Item = &'r WooCommerceRow>) -> ProductIndex<'a> {
+        let names = self.names();
+        let mut matches = HashMap::new();
+        for row in rows {
+            matches.entry(key(row))
+                .or_insert_with(|| self.find(&names, &row.sku, &row.product_name));
+        }
+        ProductIndex { catalogue: self, names, matches }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.products.iter().map(|product| normalize_name(&product.name)).collect()
+    }
+
+    /// The temperature class given by the first rule whose keyword `product_name` contains, if
+    /// any.
+    pub fn rule_temperature(&self, product_name: &str) -> Option<Temperature> {
+        let name = product_name.to_lowercase();
+        self.temperature_rules.iter()
+            .find(|rule| name.contains(&rule.keyword.to_lowercase()))
+            .map(|rule| rule.temperature)
+    }
+
+    /// The products as CSV with a header, see `dialect::semicolon_csv`, for editing.
+    pub fn text(&self) -> String {
+        let header = Column::ALL.iter().map(|column| column.aliases()[0]);
+        dialect::semicolon_csv(header, self.products.iter().map(|product| {
+            [
+                product.sku.clone(),
+                product.name.clone(),
+                product.short_name.clone(),
+                product.category.clone(),
+                product.producer.clone(),
+                format_thousandths(product.weight),
+                format_thousandths(product.volume),
+                product.temperature.map(|t| t.to_string()).unwrap_or_default(),
+                product.location.clone(),
+            ]
+        }))
+    }
+
+    /// Replaces the products with the ones in `text`, a CSV with a header that names its columns
+    /// (see `Column::aliases`; only the name is required). Rows without a name are skipped; if a
+    /// row cannot be read, the products are left as they were.
+    pub fn set_text(&mut self, text: &str) -> Result<()> {
+        let dialect = dialect::sniff(text, Dialect::default());
+        let mut reader = ReaderBuilder::new()
+            .delimiter(dialect.delimiter)
+            .quote(dialect.quote)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let header = reader.headers()?.clone();
+        let columns = Column::ALL.iter()
+            .filter_map(|&column| {
+                let aliases = column.aliases().iter().map(|alias| normalize(alias)).collect::<Vec<_>>();
+                header.iter().position(|h| aliases.contains(&normalize(h))).map(|i| (column, i))
+            })
+            .collect::<HashMap<Column, usize>>();
+        if !columns.contains_key(&Column::Name) {
+            return Err(anyhow!("The catalogue has no {:?} column", Column::Name.aliases()[0]));
+        }
+
+        let mut products = Vec::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            let line = i + 2;
+            let get = |column| columns.get(&column).and_then(|&i| record.get(i)).unwrap_or("").trim();
+            let thousandths = |column: Column| parse_thousandths(get(column))
+                .ok_or_else(|| anyhow!("Line {}: invalid {} {:?}", line, column.aliases()[0], get(column)));
+            let temperature = match get(Column::Temperature) {
+                "" => None,
+                t => Some(Temperature::parse(t).ok_or_else(|| anyhow!("Line {}: unknown temperature {:?}", line, t))?),
+            };
+            let product = Product {
+                sku: get(Column::Sku).to_owned(),
+                name: get(Column::Name).to_owned(),
+                short_name: get(Column::ShortName).to_owned(),
+                category: get(Column::Category).to_owned(),
+                producer: get(Column::Producer).to_owned(),
+                weight: thousandths(Column::Weight)?,
+                volume: thousandths(Column::Volume)?,
+                temperature,
+                location: get(Column::Location).to_owned(),
+            };
+            if !product.name.is_empty() {
+                products.push(product);
+            }
+        }
+        self.products = products;
+        Ok(())
+    }
+
+    /// Replaces the products with the ones in a file: either a JSON array of products, with
+    /// the fields of `Product` in the same units and temperature names as the CSV (see
+    /// `JsonProduct`), or a CSV as accepted by `set_text`.
+    pub fn import(&mut self, data: &[u8]) -> Result<()> {
+        if is_json(data) {
+            let mut products = Vec::new();
+            for (i, product) in serde_json::from_slice::<Vec<JsonProduct>>(data)?.into_iter().enumerate() {
+                let product = product.into_product(i + 1)?;
+                if !product.name.is_empty() {
+                    products.push(product);
+                }
+            }
+            self.products = products;
+            Ok(())
+        } else {
+            self.set_text(&dialect::decode(data).0)
+        }
+    }
+
+    /// The temperature rules as text, one `KEYWORD: temperature` per line, for editing.
+    pub fn temperature_rules_text(&self) -> String {
+        self.temperature_rules.iter()
+            .map(|rule| format!("{}: {}", rule.keyword, rule.temperature))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces the temperature rules with the ones in `text`, ignoring lines that are not
+    /// `KEYWORD: temperature`.
+    pub fn set_temperature_rules_text(&mut self, text: &str) {
+        self.temperature_rules = text.lines()
+            .filter_map(|line| {
+                let (keyword, temperature) = line.split_at(line.rfind(':')?);
+                Some(TemperatureRule { keyword: keyword.trim().to_owned(), temperature: Temperature::parse(&temperature[1..])? })
+            })
+            .filter(|rule| !rule.keyword.is_empty())
+            .collect();
+    }
+}
+
+/// A product of the catalogue found for a name in an export.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match<'a> {
+    pub product: &'a Product,
+    /// Whether it was found by a similar name, rather than by SKU or by the same name.
+    pub fuzzy: bool,
+}
+
+/// The products of the catalogue found for the rows of an export, see `Catalogue::index`.
+pub struct ProductIndex<'a> {
+    catalogue: &'a Catalogue,
+    names: Vec<String>,
+    matches: HashMap<(String, String), Option<Match<'a>>>,
+}
+
+impl<'a> ProductIndex<'a> {
+    pub fn product(&self, row: &WooCommerceRow) -> Option<&'a Product> {
+        self.matches.get(&key(row)).copied().flatten().map(|m| m.product)
+    }
+
+    /// The product called `product_name`, e.g. a component of a bundle, which has no SKU.
+    pub fn product_named(&self, product_name: &str) -> Option<&'a Product> {
+        self.catalogue.find(&self.names, "", product_name).map(|m| m.product)
+    }
+
+    /// The temperature class of the product of `row`: the one in the catalogue, or else the one
+    /// given by the temperature rules; `None` if neither gives one.
+    pub fn temperature(&self, row: &WooCommerceRow) -> Option<Temperature> {
+        self.product(row)
+            .and_then(|product| product.temperature)
+            .or_else(|| self.catalogue.rule_temperature(&row.product_name))
+    }
+
+    /// The name to print on labels for the product of `row`, see `ShortNames::shorten`.
+    pub fn label_name(&self, row: &WooCommerceRow) -> String {
+        let short_name = self.product(row).map(|product| product.short_name.as_str());
+        self.catalogue.short_names.shorten(&row.product_name, short_name)
+    }
+
+    /// The names in the export next to the names printed on labels for them, sorted.
+    pub fn label_names(&self) -> Vec<(&str, String)> {
+        let mut names = self.matches.iter()
+            .map(|((_, name), m)| {
+                let short_name = m.map(|m| m.product.short_name.as_str());
+                (name.as_str(), self.catalogue.short_names.shorten(name, short_name))
+            })
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// The names in the export that match no product, sorted.
+    pub fn unmatched(&self) -> Vec<&str> {
+        let mut names = self.matches.iter()
+            .filter(|(_, m)| m.is_none())
+            .map(|((_, name), _)| name.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// The names in the export with no temperature class, in the catalogue or by the rules,
+    /// sorted.
+    pub fn unclassified(&self) -> Vec<&str> {
+        let mut names = self.matches.iter()
+            .filter(|((_, name), m)| m.and_then(|m| m.product.temperature).is_none() && self.catalogue.rule_temperature(name).is_none())
+            .map(|((_, name), _)| name.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// The names in the export that were matched to a product by similarity, sorted, to be
+    /// checked.
+    pub fn fuzzy(&self) -> Vec<(&str, &'a Product)> {
+        let mut names = self.matches.iter()
+            .filter_map(|((_, name), m)| m.filter(|m| m.fuzzy).map(|m| (name.as_str(), m.product)))
+            .collect::<Vec<_>>();
+        names.sort_unstable_by_key(|(name, _)| *name);
+        names
+    }
+}
+
+fn key(row: &WooCommerceRow) -> (String, String) {
+    (row.sku.clone(), row.product_name.clone())
+}
+
+/// Lower-cases a product name, strips accents and punctuation, and separates numbers from units,
+/// so that `Ragù  500g` and `RAGU' 500 g` are the same.
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.to_lowercase().chars() {
+        let c = match c {
+            'à' | 'á' | 'â' | 'ä' => 'a',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ò' | 'ó' | 'ô' | 'ö' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        };
+        if let Some(p) = previous {
+            if c != ' ' && p != ' ' && p.is_ascii_digit() != c.is_ascii_digit() {
+                normalized.push(' ');
+            }
+        }
+        normalized.push(c);
+        previous = Some(c);
+    }
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn numbers(normalized: &str) -> Vec<&str> {
+    let mut numbers = normalized.split(' ')
+        .filter(|word| word.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>();
+    numbers.sort_unstable();
+    numbers
+}
+
+/// The Sørensen–Dice coefficient of the pairs of adjacent characters of two names: 1 if they
+/// are the same, 0 if they have no pair in common.
+fn similarity(a: &str, b: &str) -> f64 {
+    let pairs = |s: &str| {
+        let chars = s.chars().collect::<Vec<_>>();
+        chars.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>()
+    };
+    let (a, mut b) = (pairs(a), pairs(b));
+    let total = a.len() + b.len();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut common = 0;
+    for pair in &a {
+        if let Some(i) = b.iter().position(|p| p == pair) {
+            b.swap_remove(i);
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / total as f64
+}
+
+/// Parses an amount of kilograms or litres, with `,` or `.` as decimal separator, into grams or
+/// millilitres. An empty string is zero.
+pub fn parse_thousandths(s: &str) -> Option<u32> {
+    let s = s.trim().replace(',', ".");
+    if s.is_empty() {
+        return Some(0);
+    }
+    let value: f64 = s.parse().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Some((value * 1000.0).round() as u32)
+}
+
+/// Grams or millilitres as kilograms or litres, without trailing zeros, e.g. `2,35`.
+pub fn format_thousandths(thousandths: u32) -> String {
+    let fraction = format!("{:03}", thousandths % 1000);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (thousandths / 1000).to_string()
+    } else {
+        format!("{},{}", thousandths / 1000, fraction)
+    }
+}
+
+#[cfg(test)]
+const CATALOGUE: &str = "\
+Codice;Nome;Nome breve;Categoria;Produttore;Peso (kg);Temperatura;Scaffale
+GAL-1;GALLETTO VALLE SPLUGA ALLE ERBE 500 g;Galletto;carne;Valle Spluga;0,5;surgelato;F1
+;Ragù di manzo 500g;;carne;;0,55;;A2
+;PATATE 5 kg;;verdura;;5;;
+";
+
+#[test]
+fn test_catalogue_text() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text(CATALOGUE).unwrap();
+    assert_eq!(catalogue.products.len(), 3);
+    assert_eq!(catalogue.products[0], Product {
+        sku: "GAL-1".to_owned(),
+        name: "GALLETTO VALLE SPLUGA ALLE ERBE 500 g".to_owned(),
+        short_name: "Galletto".to_owned(),
+        category: "carne".to_owned(),
+        producer: "Valle Spluga".to_owned(),
+        weight: 500,
+        volume: 0,
+        temperature: Some(Temperature::Frozen),
+        location: "F1".to_owned(),
+    });
+
+    let mut copy = Catalogue::default();
+    copy.set_text(&catalogue.text()).unwrap();
+    assert_eq!(copy, catalogue);
+    assert!(catalogue.text().starts_with("SKU;Name;Short Name;Category;Producer;Weight (kg);Volume (l);Temperature;Location\n"));
+
+    assert!(copy.set_text("Nome;Peso\nPANE;mezzo chilo").is_err());
+    assert!(copy.set_text("Codice;Peso\nX;1").is_err());
+    assert_eq!(copy, catalogue);
+}
+
+#[test]
+fn test_import_json() {
+    let mut catalogue = Catalogue::default();
+    catalogue.import(br#"[{"sku": "MIE-1", "name": "MIELE 500 g", "weight": 0.65, "temperature": "secco"},
+        {"name": "PASSATA 1 l", "weight": "1,05", "volume": 1, "temperature": "Ambient"}]"#).unwrap();
+    assert_eq!(catalogue.products[0].weight, 650);
+    assert_eq!(catalogue.products[0].temperature, Some(Temperature::Ambient));
+    assert_eq!((catalogue.products[1].weight, catalogue.products[1].volume), (1050, 1000));
+    assert_eq!(catalogue.products[1].temperature, Some(Temperature::Ambient));
+    assert!(catalogue.import(br#"[{"name": "MIELE 500 g", "temperature": "tiepido"}]"#).is_err());
+    catalogue.import(CATALOGUE.as_bytes()).unwrap();
+    assert_eq!(catalogue.products.len(), 3);
+}
+
+#[test]
+fn test_find() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text(CATALOGUE).unwrap();
+    let names = catalogue.names();
+    let found = |sku, name| catalogue.find(&names, sku, name).map(|m| (m.product.name.as_str(), m.fuzzy));
+    assert_eq!(found("gal-1", "whatever"), Some(("GALLETTO VALLE SPLUGA ALLE ERBE 500 g", false)));
+    assert_eq!(found("", "RAGU' DI MANZO 500 G"), Some(("Ragù di manzo 500g", false)));
+    assert_eq!(found("", "GALLETTO VALLE SPLUGA ALLE ERBE DI MONTAGNA 500 g"), Some(("GALLETTO VALLE SPLUGA ALLE ERBE 500 g", true)));
+    assert_eq!(found("", "GALLETTO VALLE SPLUGA ALLE ERBE 1 kg"), None);
+    assert_eq!(found("", "PANE"), None);
+}
+
+#[test]
+fn test_temperature_rules() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_temperature_rules_text("yogurt: chilled\ngalletto: chilled\nburro: freddo");
+    assert_eq!(catalogue.temperature_rules_text(), "yogurt: chilled\ngalletto: chilled");
+    assert_eq!(catalogue.rule_temperature("YOGURT DI CAPRA 500 g"), Some(Temperature::Chilled));
+    assert_eq!(catalogue.rule_temperature("PANE"), None);
+}
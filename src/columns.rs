@@ -0,0 +1,260 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::profiles::ColumnProfile;
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// A field of `WooCommerceRow`, as found in one column of the export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Field {
+    OrderId,
+    OrderDate,
+    /// Not read by the labels; kept so that the profiles saved with a header for it still load.
+    OrderStatus,
+    CustomerName,
+    OrderTotal,
+    OrderShipping,
+    PaymentGateway,
+    ShippingMethod,
+    ShippingAddressLine1,
+    ShippingAddressLine2,
+    ShippingPostcode,
+    BillingPhoneNumber,
+    TransactionId,
+    ProductName,
+    Sku,
+    Quantity,
+    ItemPrice,
+}
+
+impl Field {
+    pub const ALL: [Field; 17] = [
+        Field::OrderId,
+        Field::OrderDate,
+        Field::OrderStatus,
+        Field::CustomerName,
+        Field::OrderTotal,
+        Field::OrderShipping,
+        Field::PaymentGateway,
+        Field::ShippingMethod,
+        Field::ShippingAddressLine1,
+        Field::ShippingAddressLine2,
+        Field::ShippingPostcode,
+        Field::BillingPhoneNumber,
+        Field::TransactionId,
+        Field::ProductName,
+        Field::Sku,
+        Field::Quantity,
+        Field::ItemPrice,
+    ];
+
+    /// The header used by the default (English) export template.
+    pub fn name(self) -> &'static str {
+        self.aliases()[0]
+    }
+
+    /// Header names known to map to this field, in the English and Italian export templates.
+    /// Matching is case-insensitive, see `normalize`. Bare words such as `Totale` or `Prezzo` are
+    /// left out: in other exports they name other columns, e.g. the line total.
+    pub fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Field::OrderId => &["Order ID", "Order Number", "ID Ordine", "Numero Ordine", "N. Ordine"],
+            Field::OrderDate => &["Order Date", "Data Ordine"],
+            Field::OrderStatus => &["Order Status", "Stato Ordine"],
+            Field::CustomerName => &["Customer Name", "Nome Cliente", "Cliente"],
+            Field::OrderTotal => &["Order Total", "Order Total Amount", "Totale Ordine"],
+            Field::OrderShipping => &["Order Shipping", "Order Shipping Amount", "Spedizione Ordine", "Costo Spedizione"],
+            Field::PaymentGateway => &["Payment Gateway", "Payment Method", "Metodo di Pagamento", "Metodo Pagamento"],
+            Field::ShippingMethod => &["Shipping Method", "Shipping Method Title", "Metodo di Spedizione", "Metodo Spedizione"],
+            Field::ShippingAddressLine1 => &["Shipping Address Line 1", "Shipping Address 1", "Indirizzo di Spedizione 1", "Indirizzo Spedizione 1"],
+            Field::ShippingAddressLine2 => &["Shipping Address Line 2", "Shipping Address 2", "Indirizzo di Spedizione 2", "Indirizzo Spedizione 2"],
+            Field::ShippingPostcode => &["Shipping Zip/Postcode", "Shipping Postcode", "CAP Spedizione"],
+            Field::BillingPhoneNumber => &["Billing Phone Number", "Billing Phone", "Telefono Fatturazione"],
+            Field::TransactionId => &["_transaction_id", "Transaction ID", "ID Transazione"],
+            Field::ProductName => &["Product Name", "Item Name", "Nome Prodotto", "Prodotto"],
+            Field::Sku => &["SKU", "Product SKU", "Item SKU", "Codice Prodotto"],
+            Field::Quantity => &["Quantity of items purchased", "Quantity", "Qty", "Quantità", "Quantita"],
+            Field::ItemPrice => &["Item price EXCL. tax", "Item Price", "Item Cost", "Prezzo Articolo IVA Esclusa"],
+        }
+    }
+
+    /// Whether the labels cannot be built without this column.
+    pub fn is_required(self) -> bool {
+        matches!(
+            self,
+            Field::OrderId | Field::CustomerName | Field::OrderTotal | Field::ProductName | Field::Quantity | Field::ItemPrice
+        )
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Lower-cases a header and collapses whitespace, so that `" Order  id"` matches `"Order ID"`.
+pub fn normalize(header: &str) -> String {
+    header
+        .trim_start_matches('\u{feff}')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Position of each field in the records of one particular export, as resolved from its header row.
+#[derive(Clone, Debug)]
+pub struct ColumnMap {
+    indices: HashMap<Field, usize>,
+    /// What looked wrong in the header, reported as warnings on its line.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ColumnMap {
+    /// Resolves every field by looking for the header configured in `profile`, or else for one
+    /// of its aliases, in `header`. The headers of the profile are looked for first, and a column
+    /// is read as one field only: when the first match of a field is a column already taken, it
+    /// is reported and the field is read from its next match, if any. A header of the profile
+    /// that the export lacks is reported too. Extra columns are ignored, missing optional columns
+    /// read as empty strings, and missing required columns are all reported together.
+    pub fn resolve(header: &StringRecord, profile: &ColumnProfile) -> Result<Self, MissingColumns> {
+        let names = header.iter().map(normalize).collect::<Vec<_>>();
+        let mut indices = HashMap::new();
+        let mut diagnostics = Vec::new();
+        let mut warn = |column: &str, message: String| diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            line: 1,
+            column: Some(column.to_owned()),
+            raw: column.to_owned(),
+            message,
+            record: header.iter().map(str::to_owned).collect(),
+        });
+
+        for &field in Field::ALL.iter() {
+            if let Some(configured) = profile.header(field) {
+                match names.iter().position(|h| *h == normalize(configured)) {
+                    Some(index) if indices.values().all(|&i| i != index) => {
+                        indices.insert(field, index);
+                    }
+                    Some(_) => {}
+                    None => warn(configured, format!("The profile reads {} from this column, which the file lacks", field)),
+                }
+            }
+        }
+        let unresolved = Field::ALL.iter().filter(|field| !indices.contains_key(field)).cloned().collect::<Vec<_>>();
+        for field in unresolved {
+            let matches = profile.header(field).into_iter()
+                .chain(field.aliases().iter().cloned())
+                .filter_map(|alias| names.iter().position(|h| *h == normalize(alias)))
+                .collect::<Vec<_>>();
+            if let Some(&index) = matches.first() {
+                if let Some(taken) = Field::ALL.iter().find(|f| indices.get(f) == Some(&index)) {
+                    warn(&header[index], format!("Already read as {}, not as {}", taken, field));
+                }
+            }
+            if let Some(&index) = matches.iter().find(|&&index| indices.values().all(|&i| i != index)) {
+                indices.insert(field, index);
+            }
+        }
+
+        let missing = Field::ALL.iter()
+            .filter(|f| f.is_required() && !indices.contains_key(f))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            Ok(ColumnMap { indices, diagnostics })
+        } else {
+            Err(MissingColumns(missing))
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn index(&self, field: Field) -> Option<usize> {
+        self.indices.get(&field).cloned()
+    }
+
+    /// The value of `field` in `record`, or `""` if the export has no such column.
+    pub fn get<'r>(&self, record: &'r StringRecord, field: Field) -> &'r str {
+        self.index(field)
+            .and_then(|i| record.get(i))
+            .unwrap_or("")
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingColumns(pub Vec<Field>);
+
+impl Display for MissingColumns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names = self.0.iter().map(|field| field.name()).collect::<Vec<_>>();
+        write!(f, "Missing required columns: {}", names.join(", "))
+    }
+}
+
+impl Error for MissingColumns {}
+
+#[test]
+fn test_resolve_aliases_in_any_order() {
+    let header = StringRecord::from(vec![
+        "Prodotto", "Extra", "QUANTITÀ", "Prezzo Articolo IVA Esclusa", " numero  ordine ", "Cliente", "Totale Ordine",
+    ]);
+    let columns = ColumnMap::resolve(&header, &ColumnProfile::default()).unwrap();
+    assert_eq!(columns.index(Field::ProductName), Some(0));
+    assert_eq!(columns.index(Field::Quantity), Some(2));
+    assert_eq!(columns.index(Field::OrderId), Some(4));
+    assert_eq!(columns.index(Field::ShippingPostcode), None);
+
+    let record = StringRecord::from(vec!["MIELE", "x", "2", "5", "42", "Rossi", "10"]);
+    assert_eq!(columns.get(&record, Field::CustomerName), "Rossi");
+    assert_eq!(columns.get(&record, Field::ShippingPostcode), "");
+}
+
+#[test]
+fn test_report_all_missing_columns() {
+    let header = StringRecord::from(vec!["Order ID", "Product Name", "Order Total"]);
+    let missing = ColumnMap::resolve(&header, &ColumnProfile::default()).unwrap_err();
+    assert_eq!(missing.0, vec![Field::CustomerName, Field::Quantity, Field::ItemPrice]);
+    assert_eq!(missing.to_string(), "Missing required columns: Customer Name, Quantity of items purchased, Item price EXCL. tax");
+}
+
+#[test]
+fn test_profile_header_takes_precedence() {
+    let header = StringRecord::from(vec!["Order ID", "Codice", "Cliente", "Totale Ordine", "Articolo", "Pezzi", "Item Price"]);
+    let mut profile = ColumnProfile::new("Bergamo");
+    profile.set_header(Field::OrderId, "codice".to_owned());
+    profile.set_header(Field::ProductName, "Articolo".to_owned());
+    profile.set_header(Field::Quantity, "Pezzi".to_owned());
+    let columns = ColumnMap::resolve(&header, &profile).unwrap();
+    assert_eq!(columns.index(Field::OrderId), Some(1));
+    assert_eq!(columns.index(Field::ProductName), Some(4));
+    assert_eq!(columns.index(Field::Quantity), Some(5));
+    assert_eq!(columns.index(Field::ItemPrice), Some(6));
+}
+
+#[test]
+fn test_one_field_per_column() {
+    let header = StringRecord::from(vec![
+        "Order ID", "Cliente", "Totale", "Totale Ordine", "Prodotto", "Quantità", "Prezzo", "Prezzo Articolo IVA Esclusa", "Note", "CAP Spedizione",
+    ]);
+    let mut profile = ColumnProfile::new("Bergamo");
+    profile.set_header(Field::PaymentGateway, "Note".to_owned());
+    profile.set_header(Field::ShippingMethod, "note".to_owned());
+    profile.set_header(Field::ShippingPostcode, "Codice Postale".to_owned());
+    let columns = ColumnMap::resolve(&header, &profile).unwrap();
+    assert_eq!(columns.index(Field::OrderTotal), Some(3));
+    assert_eq!(columns.index(Field::ItemPrice), Some(7));
+    assert_eq!(columns.index(Field::PaymentGateway), Some(8));
+    assert_eq!(columns.index(Field::ShippingMethod), None);
+    assert_eq!(columns.index(Field::ShippingPostcode), Some(9));
+    let messages = columns.diagnostics().iter().map(|d| (d.column.as_deref().unwrap_or(""), d.message.as_str())).collect::<Vec<_>>();
+    assert_eq!(messages, vec![
+        ("Codice Postale", "The profile reads Shipping Zip/Postcode from this column, which the file lacks"),
+        ("Note", "Already read as Payment Gateway, not as Shipping Method"),
+    ]);
+}
@@ -0,0 +1,49 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The row was read, but something looks wrong with it.
+    Warning,
+    /// The row could not be read, and is left out of the labels.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        })
+    }
+}
+
+/// A problem found in one line of an export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line in the file; the header is line 1.
+    pub line: u64,
+    /// Header of the offending column, if the problem is about a single value.
+    pub column: Option<String>,
+    /// The offending value, or the whole line if the problem is not about a single value.
+    pub raw: String,
+    pub message: String,
+    /// All the values of the offending line, to show it in context.
+    pub record: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {}", self.severity, self.line)?;
+        if let Some(column) = &self.column {
+            write!(f, ", column {}", column)?;
+        }
+        write!(f, ": {} ({:?})", self.message, self.raw)
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::{Display, Formatter};
+
+/// How many records are looked at to guess the delimiter.
+const SNIFF_RECORDS: usize = 20;
+const DELIMITERS: [u8; 3] = [b',', b';', b'\t'];
+const QUOTES: [u8; 2] = [b'"', b'\''];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// What Excel uses on Italian Windows machines. A superset of Latin-1.
+    Windows1252,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Windows1252 => "Windows-1252",
+        })
+    }
+}
+
+/// The flavour of CSV found in an export, as guessed by `decode` and `sniff`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect { delimiter: b',', quote: b'"', encoding: Encoding::Utf8, bom: false }
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let delimiter = match self.delimiter {
+            b'\t' => "tab".to_owned(),
+            d => format!("'{}'", d as char),
+        };
+        write!(f, "delimiter {}, quote {}, encoding {}", delimiter, self.quote as char, self.encoding)?;
+        if self.bom {
+            f.write_str(", with BOM")?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns the raw bytes of an export into text, stripping the byte order mark if any. Bytes that
+/// are not valid UTF-8 are assumed to be Windows-1252, which is how Excel re-saves CSV files.
+pub fn decode(bytes: &[u8]) -> (String, Dialect) {
+    let (bytes, bom) = if bytes.starts_with(b"\xef\xbb\xbf") {
+        (&bytes[3..], true)
+    } else {
+        (bytes, false)
+    };
+    let (text, encoding) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_owned(), Encoding::Utf8),
+        Err(_) => (bytes.iter().map(|&b| windows_1252_char(b)).collect(), Encoding::Windows1252),
+    };
+    (text, Dialect { bom, encoding, ..Dialect::default() })
+}
+
+/// Writes `header` and `rows` as CSV with `;` as delimiter, as Excel expects it in Italy, where
+/// the comma is the decimal separator.
+pub fn semicolon_csv<H, R>(header: H, rows: impl IntoIterator<Item = R>) -> String
+where
+    H: IntoIterator,
+    H::Item: AsRef<[u8]>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::WriterBuilder::new().delimiter(b';').from_writer(Vec::new());
+    writer.write_record(header).expect("writing to memory");
+    for row in rows {
+        writer.write_record(row).expect("writing to memory");
+    }
+    String::from_utf8(writer.into_inner().expect("writing to memory")).expect("CSV of strings")
+}
+
+/// `semicolon_csv` behind a byte order mark, for the files that are downloaded: without it, Excel
+/// reads UTF-8 as Windows-1252 and garbles the accents.
+pub fn excel_csv<H, R>(header: H, rows: impl IntoIterator<Item = R>) -> String
+where
+    H: IntoIterator,
+    H::Item: AsRef<[u8]>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    format!("\u{feff}{}", semicolon_csv(header, rows))
+}
+
+/// Guesses the delimiter and quote character of `text`, keeping the encoding found by `decode`.
+pub fn sniff(text: &str, dialect: Dialect) -> Dialect {
+    let quote = sniff_quote(text);
+    let delimiter = DELIMITERS.iter()
+        .map(|&delimiter| (delimiter, score(text, delimiter, quote)))
+        .filter(|(_, (_, header_fields))| *header_fields > 0)
+        .max_by_key(|(_, score)| *score)
+        .map(|(delimiter, _)| delimiter)
+        .unwrap_or(b',');
+    Dialect { delimiter, quote, ..dialect }
+}
+
+/// Quotes are recognised at the start of a field, so that apostrophes in product names like
+/// `RAGU' E POLPETTE` do not count.
+fn sniff_quote(text: &str) -> u8 {
+    let bytes = text.as_bytes();
+    let opening = |quote: u8| {
+        bytes.iter().enumerate()
+            .filter(|&(i, &b)| b == quote && (i == 0 || DELIMITERS.contains(&bytes[i - 1]) || bytes[i - 1] == b'\n'))
+            .count()
+    };
+    QUOTES.iter()
+        .cloned()
+        .max_by_key(|&quote| (opening(quote), quote == b'"'))
+        .unwrap_or(b'"')
+}
+
+/// The number of records that have as many fields as the header, and the number of delimiters
+/// in the header. A good delimiter splits every record in the same number of fields.
+fn score(text: &str, delimiter: u8, quote: u8) -> (usize, usize) {
+    let counts = delimiters_per_record(text, delimiter, quote);
+    let header = counts.first().cloned().unwrap_or(0);
+    let consistent = counts.iter().filter(|&&c| c == header).count();
+    (consistent, header)
+}
+
+fn delimiters_per_record(text: &str, delimiter: u8, quote: u8) -> Vec<usize> {
+    let mut counts = Vec::new();
+    let (mut in_quotes, mut count) = (false, 0);
+    for &b in text.as_bytes() {
+        if b == quote {
+            in_quotes = !in_quotes;
+        } else if b == delimiter && !in_quotes {
+            count += 1;
+        } else if b == b'\n' && !in_quotes {
+            counts.push(count);
+            count = 0;
+            if counts.len() == SNIFF_RECORDS {
+                return counts;
+            }
+        }
+    }
+    if count > 0 {
+        counts.push(count);
+    }
+    counts
+}
+
+fn windows_1252_char(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+        '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+    ];
+    match b {
+        0x80..=0x9f => HIGH[(b - 0x80) as usize],
+        _ => b as char,
+    }
+}
+
+#[test]
+fn test_sniff_excel_export() {
+    let bytes = b"Order ID;Customer Name;Order Total\r\n5358;PERIN\xd2 LUPO;57,10\r\n5357;\"Maria; Luisa\";57,90\r\n";
+    let (text, dialect) = decode(bytes);
+    let dialect = sniff(&text, dialect);
+    assert_eq!(dialect.delimiter, b';');
+    assert_eq!(dialect.quote, b'"');
+    assert_eq!(dialect.encoding, Encoding::Windows1252);
+    assert!(text.contains("PERINÒ LUPO"));
+    assert_eq!(dialect.to_string(), "delimiter ';', quote \", encoding Windows-1252");
+}
+
+#[test]
+fn test_sniff_tab_with_bom() {
+    let (text, dialect) = decode("\u{feff}Order ID\tProduct Name\n1\tRAGU' E POLPETTE, 500 g\n".as_bytes());
+    let dialect = sniff(&text, dialect);
+    assert!(text.starts_with("Order ID"));
+    assert_eq!(dialect, Dialect { delimiter: b'\t', quote: b'"', encoding: Encoding::Utf8, bom: true });
+}
+
+#[test]
+fn test_excel_csv() {
+    let csv = excel_csv(["Prodotto", "Quantità"], vec![["RAGÙ; 500 g", "2"]]);
+    assert!(csv.as_bytes().starts_with(b"\xef\xbb\xbfProdotto;Quantit\xc3\xa0\n"));
+    let (text, dialect) = decode(csv.as_bytes());
+    assert_eq!(text, "Prodotto;Quantità\n\"RAGÙ; 500 g\";2\n");
+    assert_eq!(sniff(&text, dialect), Dialect { delimiter: b';', quote: b'"', encoding: Encoding::Utf8, bom: true });
+}
+
+#[test]
+fn test_sniff_single_quotes() {
+    let (text, dialect) = decode(b"'Order ID','Product Name'\n1,'PANE, 500 g'\n");
+    let dialect = sniff(&text, dialect);
+    assert_eq!(dialect.delimiter, b',');
+    assert_eq!(dialect.quote, b'\'');
+}
@@ -0,0 +1,570 @@
+use crate::barcode::{self, QrCode};
+use crate::catalogue::Temperature;
+use crate::pdf::{Document, Font, Page};
+use crate::woocsv::OrderDetails;
+use serde::{Deserialize, Serialize};
+
+pub const LABELS_KEY: &str = "campagnamica.labels";
+
+const REGULAR: usize = 0;
+const BOLD: usize = 1;
+const MARGIN: f64 = 4.0;
+/// Width of the quantity column of the items.
+const QUANTITY_WIDTH: f64 = 16.0;
+const LINE: f64 = 3.6;
+/// Labels less tall than this only have the address, see `draw_address_label`.
+const FULL_LABEL_HEIGHT: f64 = 90.0;
+
+/// The size of the pages of the PDF labels, one label per page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelSize {
+    #[default]
+    A6,
+    Label100x150,
+}
+
+impl LabelSize {
+    pub const ALL: [LabelSize; 2] = [LabelSize::A6, LabelSize::Label100x150];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LabelSize::A6 => "A6 (105 × 148 mm)",
+            LabelSize::Label100x150 => "100 × 150 mm",
+        }
+    }
+
+    /// Width and height, in millimetres.
+    pub fn dimensions(self) -> (f64, f64) {
+        match self {
+            LabelSize::A6 => (105.0, 148.0),
+            LabelSize::Label100x150 => (100.0, 150.0),
+        }
+    }
+
+    /// A page of this size holding a single label.
+    pub fn layout(self) -> SheetLayout {
+        let (width, height) = self.dimensions();
+        SheetLayout {
+            name: self.name().to_owned(),
+            page_width: width,
+            page_height: height,
+            columns: 1,
+            rows: 1,
+            label_width: width,
+            label_height: height,
+            top: 0.0,
+            left: 0.0,
+            column_gap: 0.0,
+            row_gap: 0.0,
+        }
+    }
+}
+
+/// A sheet of adhesive labels: a grid of `columns` × `rows` labels, the first `top` millimetres
+/// from the top edge and `left` from the left edge, with gaps between them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SheetLayout {
+    pub name: String,
+    pub page_width: f64,
+    pub page_height: f64,
+    pub columns: u32,
+    pub rows: u32,
+    pub label_width: f64,
+    pub label_height: f64,
+    pub top: f64,
+    pub left: f64,
+    pub column_gap: f64,
+    pub row_gap: f64,
+}
+
+impl SheetLayout {
+    /// Common A4 sheets.
+    pub fn builtin() -> Vec<SheetLayout> {
+        let a4 = |name: &str, (columns, rows): (u32, u32), (label_width, label_height): (f64, f64), (top, left): (f64, f64), column_gap: f64| SheetLayout {
+            name: name.to_owned(),
+            page_width: 210.0,
+            page_height: 297.0,
+            columns,
+            rows,
+            label_width,
+            label_height,
+            top,
+            left,
+            column_gap,
+            row_gap: 0.0,
+        };
+        vec![
+            a4("A4 2×2, 105 × 148,5 mm (Avery 3483)", (2, 2), (105.0, 148.5), (0.0, 0.0), 0.0),
+            a4("A4 2×4, 99,1 × 67,7 mm (Avery L7165)", (2, 4), (99.1, 67.7), (13.1, 4.65), 2.5),
+            a4("A4 2×7, 99,1 × 38,1 mm (Avery L7163)", (2, 7), (99.1, 38.1), (15.15, 4.65), 2.5),
+            a4("A4 3×7, 63,5 × 38,1 mm (Avery L7160)", (3, 7), (63.5, 38.1), (15.15, 7.25), 2.5),
+            a4("A4 3×8, 70 × 37 mm (Herma 4464)", (3, 8), (70.0, 37.0), (0.5, 0.0), 0.0),
+        ]
+    }
+
+    pub fn cells(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// The `index`-th label of the sheet, counting along the rows from the top left one.
+    pub fn cell(&self, index: usize) -> Area {
+        let (row, column) = (index / self.columns as usize, index % self.columns as usize);
+        Area {
+            x: self.left + column as f64 * (self.label_width + self.column_gap),
+            y: self.top + row as f64 * (self.label_height + self.row_gap),
+            width: self.label_width,
+            height: self.label_height,
+        }
+    }
+
+    /// Whether the labels are all on the page.
+    fn fits(&self) -> bool {
+        let last = self.cell(self.cells().saturating_sub(1));
+        self.cells() > 0 && self.label_width > 0.0 && self.label_height > 0.0
+            && last.x + last.width <= self.page_width + 0.01 && last.y + last.height <= self.page_height + 0.01
+    }
+
+    /// The layout as a line of text, see `LabelOptions::set_custom_sheets_text`.
+    fn text(&self) -> String {
+        format!("{}; {}x{}; {}x{}; {}x{}; {}; {}; {}; {}",
+            self.name, number(self.page_width), number(self.page_height), self.columns, self.rows,
+            number(self.label_width), number(self.label_height), number(self.top), number(self.left),
+            number(self.column_gap), number(self.row_gap))
+    }
+
+    fn parse(line: &str) -> Option<SheetLayout> {
+        let fields = line.split(';').map(str::trim).collect::<Vec<_>>();
+        if fields.len() != 8 || fields[0].is_empty() {
+            return None;
+        }
+        let pair = |field: &str| -> Option<(f64, f64)> {
+            let (a, b) = field.split_once(['x', 'X', '×'])?;
+            Some((parse_number(a)?, parse_number(b)?))
+        };
+        let (page_width, page_height) = pair(fields[1])?;
+        let (columns, rows) = pair(fields[2]).filter(|(columns, rows)| columns.fract() == 0.0 && rows.fract() == 0.0)?;
+        let (label_width, label_height) = pair(fields[3])?;
+        let layout = SheetLayout {
+            name: fields[0].to_owned(),
+            page_width,
+            page_height,
+            columns: columns as u32,
+            rows: rows as u32,
+            label_width,
+            label_height,
+            top: parse_number(fields[4])?,
+            left: parse_number(fields[5])?,
+            column_gap: parse_number(fields[6])?,
+            row_gap: parse_number(fields[7])?,
+        };
+        Some(layout).filter(SheetLayout::fits)
+    }
+}
+
+/// Millimetres with a decimal comma, without trailing zeros.
+fn number(mm: f64) -> String {
+    format!("{}", (mm * 100.0).round() / 100.0).replace('.', ",")
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().replace(',', ".").parse().ok().filter(|n: &f64| *n >= 0.0)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LabelOptions {
+    /// The size of the pages when printing one label per page.
+    pub size: LabelSize,
+    /// The name of the sheet of labels to print on, if any, instead of one label per page.
+    pub sheet: Option<String>,
+    /// Sheets measured by the user, besides the built-in ones.
+    pub custom_sheets: Vec<SheetLayout>,
+}
+
+impl LabelOptions {
+    /// The built-in sheets and the custom ones.
+    pub fn sheets(&self) -> Vec<SheetLayout> {
+        SheetLayout::builtin().into_iter().chain(self.custom_sheets.iter().cloned()).collect()
+    }
+
+    /// The chosen sheet, or else a page of the chosen size.
+    pub fn layout(&self) -> SheetLayout {
+        self.sheet.as_ref()
+            .and_then(|name| self.sheets().into_iter().find(|sheet| sheet.name == *name))
+            .unwrap_or_else(|| self.size.layout())
+    }
+
+    /// The custom sheets as text, one per line, for editing.
+    pub fn custom_sheets_text(&self) -> String {
+        self.custom_sheets.iter().map(SheetLayout::text).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Replaces the custom sheets with the ones in `text`, one per line as
+    /// `NAME; page width x height; columns x rows; label width x height; top; left; column gap; row gap`
+    /// in millimetres, ignoring the lines that are not like that or whose labels do not fit on
+    /// the page.
+    pub fn set_custom_sheets_text(&mut self, text: &str) {
+        self.custom_sheets = text.lines().filter_map(SheetLayout::parse).collect();
+    }
+}
+
+/// The fonts of the labels, DejaVu Sans, embedded in the PDF so that it prints the same
+/// everywhere.
+pub struct LabelFonts {
+    regular: Font,
+    bold: Font,
+}
+
+impl LabelFonts {
+    /// Parses the embedded fonts, once for all the PDFs: keep them.
+    pub fn load() -> LabelFonts {
+        LabelFonts {
+            regular: Font::parse("DejaVuSans", include_bytes!("../fonts/DejaVuSans-WinAnsi.ttf")).expect("embedded font"),
+            bold: Font::parse("DejaVuSans-Bold", include_bytes!("../fonts/DejaVuSans-Bold-WinAnsi.ttf")).expect("embedded font"),
+        }
+    }
+
+    /// A document using these fonts.
+    pub fn document(&self) -> Document<'_> {
+        Document::new(vec![&self.regular, &self.bold])
+    }
+
+    fn font(&self, font: usize) -> &Font {
+        if font == BOLD { &self.bold } else { &self.regular }
+    }
+}
+
+/// A rectangle of a page, in millimetres from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Area {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The labels of `orders` as a PDF, one for each package, in the successive labels of the
+/// layout of `options`, leaving out the first `skip` labels of the first sheet, which are already
+/// used. Packages with more items than a label holds go on more labels.
+pub fn labels_pdf(orders: &[OrderDetails], fonts: &LabelFonts, options: &LabelOptions, skip: usize) -> Vec<u8> {
+    let mut document = fonts.document();
+    let layout = options.layout();
+    let mut page: Option<Page> = None;
+    let mut cell = skip % layout.cells();
+    for order in orders {
+        for package in 0..order.packages.len() {
+            let mut first = 0;
+            loop {
+                let sheet = page.get_or_insert_with(|| Page::new(layout.page_width, layout.page_height));
+                first = draw_label(sheet, fonts, order, package, first, layout.cell(cell));
+                cell += 1;
+                if cell == layout.cells() {
+                    document.add_page(page.take().expect("a page being drawn"));
+                    cell = 0;
+                }
+                if first >= order.packages[package].len() {
+                    break;
+                }
+            }
+        }
+    }
+    if let Some(page) = page {
+        document.add_page(page);
+    }
+    document.to_bytes()
+}
+
+/// Draws in `area` the label of the `package`-th package of `order`, with the items from
+/// `first` on, as the HTML label shows it. Returns the index of the first item that did not fit,
+/// or the number of items if all did; at least one item is drawn, so that the labels of a
+/// package always end. Small labels only get the address.
+pub fn draw_label(page: &mut Page, fonts: &LabelFonts, order: &OrderDetails, package: usize, first: usize, area: Area) -> usize {
+    if area.height < FULL_LABEL_HEIGHT {
+        draw_address_label(page, fonts, order, package, area);
+        return order.packages[package].len();
+    }
+    let left = area.x + MARGIN;
+    let width = area.width - 2.0 * MARGIN;
+    let bottom = area.y + area.height - MARGIN;
+    let mut y = area.y + MARGIN;
+    let items = &order.packages[package];
+    let centered = |page: &mut Page, font: usize, size: f64, y: f64, text: &str| {
+        let text = fonts.font(font).truncate(text, size, width);
+        page.text(left + (width - fonts.font(font).width(&text, size)) / 2.0, y, font, size, &text);
+    };
+
+    if let Some(temperature) = order.package_temperature(package) {
+        match temperature {
+            Temperature::Frozen => page.rect(left, y, width, 7.0, 0.0),
+            Temperature::Chilled => {
+                page.rect(left, y, width, 7.0, 0.3);
+                page.rect(left + 0.8, y + 0.8, width - 1.6, 5.4, 0.3);
+            }
+            Temperature::Ambient => page.rect(left, y, width, 7.0, 0.5),
+        }
+        if temperature == Temperature::Frozen {
+            page.gray(1.0);
+        }
+        centered(page, BOLD, 10.0, y + 5.0, temperature.banner());
+        page.gray(0.0);
+    }
+    y += 9.0;
+
+    let column = left + width * 0.55;
+    let details = [
+        format!("Ordine N.: {}", order.order_id),
+        format!("Data: {}", order.order_date),
+        format!("Tel.: {}", order.billing_phone_number),
+    ];
+    for (i, line) in details.iter().enumerate() {
+        page.text(left, y + 3.0 + LINE * i as f64, REGULAR, 8.0, &fonts.regular.truncate(line, 8.0, column - left - 1.0));
+    }
+    page.text(column, y + 3.0, BOLD, 8.0, "Indirizzo:");
+    let address = [&order.customer_name, &order.shipping_address_line_1, &order.shipping_address_line_2, &format!("Milano, {}", order.shipping_postcode), &"Italia".to_owned()]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| fonts.regular.truncate(line, 8.0, left + width - column))
+        .collect::<Vec<_>>();
+    for (i, line) in address.iter().enumerate() {
+        page.text(column, y + 3.0 + LINE * (i + 1) as f64, REGULAR, 8.0, line);
+    }
+    let codes_bottom = draw_package_code(page, fonts, order, package, left, y + 3.0 + LINE * 2.0, column - left - 2.0);
+    y = (y + LINE * (address.len() + 1) as f64 + 2.0).max(codes_bottom);
+
+    let continued = if first > 0 { " (segue)" } else { "" };
+    page.text(column, y + 4.0, BOLD, 11.0, &format!("Collo {} di {}{}", package + 1, order.packages.len(), continued));
+    y += 7.0;
+
+    page.rect(left, y, width, 5.5, 0.0);
+    page.gray(1.0);
+    page.text(left + 1.0, y + 4.0, BOLD, 8.0, "Quantità");
+    page.text(left + QUANTITY_WIDTH + 1.0, y + 4.0, BOLD, 8.0, "Prodotto");
+    page.gray(0.0);
+    y += 5.5;
+
+    let delivery = order.delivery_details(package);
+    let details_height = 4.2 * delivery.len() as f64 + 8.0;
+    let name_width = width - QUANTITY_WIDTH - 2.0;
+    let mut next = first;
+    while next < items.len() {
+        let item = &items[next];
+        let names = fonts.bold.wrap(&item.label_name, 9.0, name_width);
+        let height = 4.0 * names.len() as f64 + 3.2 * item.components.len() as f64 + 1.5;
+        if next > first && y + height > bottom - details_height {
+            break;
+        }
+        let quantity = if item.ordered > item.quantity { format!("{} di {}", item.quantity, item.ordered) } else { item.quantity.to_string() };
+        page.text(left + (QUANTITY_WIDTH - fonts.regular.width(&quantity, 9.0)) / 2.0, y + 4.0, REGULAR, 9.0, &quantity);
+        for (i, name) in names.iter().enumerate() {
+            page.text(left + QUANTITY_WIDTH + 1.0, y + 4.0 * (i + 1) as f64, BOLD, 9.0, name);
+        }
+        for (i, component) in item.components.iter().enumerate() {
+            let text = fonts.regular.truncate(&format!("{} {}", component.quantity, component.product_name), 7.0, name_width - 3.0);
+            page.text(left + QUANTITY_WIDTH + 4.0, y + 4.0 * names.len() as f64 + 3.2 * (i + 1) as f64, REGULAR, 7.0, &text);
+        }
+        y += height;
+        page.gray(0.8);
+        page.line(left, y, left + width, y, 0.2);
+        page.gray(0.0);
+        next += 1;
+    }
+    if next < items.len() {
+        centered(page, BOLD, 9.0, y + 5.0, "Continua sull'etichetta seguente");
+        return next;
+    }
+
+    y += 1.0;
+    for detail in delivery.iter() {
+        let (font, size) = if detail.highlight { (BOLD, 10.0) } else { (REGULAR, 8.0) };
+        page.text(left + width * 0.4 - fonts.bold.width(detail.name, 8.0), y + 3.5, BOLD, 8.0, detail.name);
+        page.text(left + width * 0.45, y + 3.5, font, size, &fonts.font(font).truncate(&detail.data, size, width * 0.55));
+        y += 4.2;
+    }
+    page.line(left + width * 0.45, y + 5.0, left + width, y + 5.0, 0.2);
+    next
+}
+
+/// Draws in `area` the temperature, the order and package numbers and the address of the
+/// `package`-th package of `order`, leaving out the lines that do not fit.
+fn draw_address_label(page: &mut Page, fonts: &LabelFonts, order: &OrderDetails, package: usize, area: Area) {
+    let margin = MARGIN.min(area.width / 20.0);
+    let left = area.x + margin;
+    let mut width = area.width - 2.0 * margin;
+    let bottom = area.y + area.height - margin / 2.0;
+    let mut y = area.y + margin;
+
+    let temperature = order.package_temperature(package).unwrap_or(Temperature::Ambient);
+    if temperature != Temperature::Ambient {
+        page.rect(left, y, width, 5.0, if temperature == Temperature::Frozen { 0.0 } else { 0.4 });
+        if temperature == Temperature::Frozen {
+            page.gray(1.0);
+        }
+        let banner = fonts.bold.truncate(temperature.banner(), 8.0, width);
+        page.text(left + (width - fonts.bold.width(&banner, 8.0)) / 2.0, y + 3.7, BOLD, 8.0, &banner);
+        page.gray(0.0);
+        y += 6.0;
+    }
+
+    let lines = [
+        (BOLD, 9.0, format!("Ordine N. {} - Collo {} di {}", order.order_id, package + 1, order.packages.len())),
+        (BOLD, 9.0, order.customer_name.clone()),
+        (REGULAR, 8.0, order.shipping_address_line_1.clone()),
+        (REGULAR, 8.0, order.shipping_address_line_2.clone()),
+        (REGULAR, 8.0, format!("Milano, {}", order.shipping_postcode)),
+        (REGULAR, 8.0, format!("Tel.: {}", order.billing_phone_number)),
+    ];
+    let qr = QrCode::encode(&order.package_code(package).to_string());
+    for (i, (font, size, line)) in lines.iter().filter(|(_, _, line)| !line.trim().is_empty()).enumerate() {
+        let height = size * 0.42;
+        if y + height > bottom {
+            break;
+        }
+        y += height;
+        page.text(left, y, *font, *size, &fonts.font(*font).truncate(line, *size, width));
+        // The QR code goes right of the address, below the order, if its modules are large
+        // enough to scan.
+        if let (0, Some(qr)) = (i, &qr) {
+            let side = (bottom - y - 1.0).min(width * 0.3).min(16.0);
+            if side / qr.size() as f64 >= 0.33 {
+                draw_qr(page, qr, left + width - side, y + 1.0, side / qr.size() as f64);
+                width -= side + 1.0;
+            }
+        }
+    }
+}
+
+/// Draws the barcodes of the `package`-th package of `order` from `x`, `y`, at most `width` wide:
+/// a QR code, then Code 128 with the code printed below. Returns where they end, below.
+fn draw_package_code(page: &mut Page, fonts: &LabelFonts, order: &OrderDetails, package: usize, left: f64, y: f64, width: f64) -> f64 {
+    let code = order.package_code(package).to_string();
+    let mut x = left;
+    let mut height: f64 = 0.0;
+    if let Some(qr) = QrCode::encode(&code) {
+        let side = 0.45 * qr.size() as f64;
+        draw_qr(page, &qr, x, y, 0.45);
+        x += side + 3.0;
+        height = side;
+    }
+    if let Some(modules) = barcode::code128(&code) {
+        let module = ((left + width - x) / modules.len() as f64).min(0.25);
+        let bars = module * modules.len() as f64;
+        let mut start = None;
+        for (i, dark) in modules.iter().chain(&[false]).enumerate() {
+            match (start, dark) {
+                (None, true) => start = Some(i),
+                (Some(from), false) => {
+                    page.rect(x + module * from as f64, y, module * (i - from) as f64, 8.0, 0.0);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        page.text(x + (bars - fonts.regular.width(&code, 7.0)) / 2.0, y + 10.5, REGULAR, 7.0, &code);
+        height = height.max(11.0);
+    }
+    y + height + 1.0
+}
+
+/// Draws `qr` from `x`, `y` with modules `module` millimetres wide, without the quiet zone.
+fn draw_qr(page: &mut Page, qr: &QrCode, x: f64, y: f64, module: f64) {
+    for row in 0..qr.size() {
+        let mut column = 0;
+        while column < qr.size() {
+            if !qr.get(column, row) {
+                column += 1;
+                continue;
+            }
+            let from = column;
+            while column < qr.size() && qr.get(column, row) {
+                column += 1;
+            }
+            page.rect(x + module * from as f64, y + module * row as f64, module * (column - from) as f64, module, 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::catalogue::Catalogue;
+#[cfg(test)]
+use crate::packing::PackingOptions;
+#[cfg(test)]
+use crate::woocsv::{sample_data, sample_orders};
+
+#[cfg(test)]
+fn hex(text: &str) -> String {
+    format!("<{}> Tj", crate::pdf::encode(text).iter().map(|byte| format!("{:02X}", byte)).collect::<String>())
+}
+
+#[test]
+fn test_labels_pdf() {
+    let orders = sample_orders();
+    let fonts = LabelFonts::load();
+    let pdf = labels_pdf(&orders, &fonts, &LabelOptions { size: LabelSize::Label100x150, ..LabelOptions::default() }, 0);
+    let text = String::from_utf8_lossy(&pdf);
+    let packages = orders.iter().map(|order| order.packages.len()).sum::<usize>();
+    assert_eq!(text.matches("/Type /Page ").count(), packages);
+    assert!(text.contains("/MediaBox [0 0 283.46 425.20]"));
+    assert!(text.contains("/FontFile2"));
+    assert!(text.contains(&hex("Collo 1 di 2")));
+    assert!(text.contains(&hex("5358-1/2")));
+    assert!(text.contains(&hex("CARNE TRITA DI MANZO PER RAGU' E")));
+    assert!(text.contains(&hex("Quantità")));
+    // Only the subsets of the fonts are embedded.
+    assert!(pdf.len() < 150 * 1024);
+}
+
+#[test]
+fn test_long_package_continues() {
+    let mut orders = sample_data()
+        .labels(&PackingOptions { multipack: false, ..PackingOptions::default() }, &Catalogue::default());
+    let order = &mut orders[0];
+    let item = order.packages[0][0].clone();
+    order.packages[0] = (1..=40).map(|i| crate::woocsv::OrderItem { label_name: format!("PRODOTTO {}", i), ..item.clone() }).collect();
+
+    let fonts = LabelFonts::load();
+    let mut page = Page::new(105.0, 148.0);
+    let next = draw_label(&mut page, &fonts, order, 0, 0, Area { x: 0.0, y: 0.0, width: 105.0, height: 148.0 });
+    assert!(next > 1 && next < 40);
+
+    let text = String::from_utf8_lossy(&labels_pdf(&orders[..1], &fonts, &LabelOptions::default(), 0)).into_owned();
+    assert!(text.matches("/Type /Page ").count() > 1);
+    assert!(text.contains(&hex("Collo 1 di 1 (segue)")));
+    assert!((1..=40).all(|i| text.contains(&hex(&format!("PRODOTTO {}", i)))));
+}
+
+#[test]
+fn test_sheet_layouts() {
+    let l7165 = &SheetLayout::builtin()[1];
+    assert_eq!(l7165.cells(), 8);
+    let cell = l7165.cell(3);
+    assert!((cell.x - 106.25).abs() < 1e-9 && (cell.y - 80.8).abs() < 1e-9);
+    assert!(SheetLayout::builtin().iter().all(SheetLayout::fits));
+
+    let mut options = LabelOptions::default();
+    options.set_custom_sheets_text("Ufficio; 210x297; 3x8; 70x37; 0,5; 0; 0; 0\nToo big; 210x297; 3x8; 80x37; 0; 0; 0; 0\nnot a sheet");
+    assert_eq!(options.custom_sheets.len(), 1);
+    assert_eq!(options.custom_sheets_text(), "Ufficio; 210x297; 3x8; 70x37; 0,5; 0; 0; 0");
+    assert_eq!(options.layout().name, LabelSize::A6.name());
+    options.sheet = Some("Ufficio".to_owned());
+    assert_eq!(options.layout().cells(), 24);
+}
+
+#[test]
+fn test_labels_on_sheets() {
+    let orders = sample_orders();
+    let packages = orders.iter().map(|order| order.packages.len()).sum::<usize>();
+    let fonts = LabelFonts::load();
+    let options = LabelOptions { sheet: Some(SheetLayout::builtin()[0].name.clone()), ..LabelOptions::default() };
+    let pages = |skip: usize| String::from_utf8_lossy(&labels_pdf(&orders, &fonts, &options, skip)).matches("/Type /Page ").count();
+    // Four labels a sheet: five packages take two sheets, also after three used labels.
+    assert_eq!(packages, 5);
+    assert_eq!((pages(0), pages(3)), (2, 2));
+    assert!(String::from_utf8_lossy(&labels_pdf(&orders, &fonts, &options, 0)).contains("/MediaBox [0 0 595.28 841.89]"));
+
+    // Small labels only have the address, one for each package.
+    let options = LabelOptions { sheet: Some(SheetLayout::builtin()[4].name.clone()), ..LabelOptions::default() };
+    let text = String::from_utf8_lossy(&labels_pdf(&orders, &fonts, &options, 0)).into_owned();
+    assert_eq!(text.matches("/Type /Page ").count(), 1);
+    assert!(text.contains(&hex("Ordine N. 5358 - Collo 1 di 2")));
+    assert!(!text.contains(&hex("Prodotto")));
+}
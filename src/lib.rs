@@ -1,6 +1,27 @@
 #![recursion_limit="2000"]
 
 mod app;
+mod barcode;
+mod bundles;
+mod catalogue;
+mod columns;
+mod diagnostics;
+mod dialect;
+mod labelpdf;
+mod manifest;
+mod money;
+mod packing;
+mod pdf;
+mod persist;
+mod picklist;
+mod profiles;
+mod reconcile;
+mod scanning;
+mod shortnames;
+mod spreadsheet;
+mod summary;
+mod thermal;
+mod wooapi;
 mod woocsv;
 
 use wasm_bindgen::prelude::*;
@@ -0,0 +1,96 @@
+use crate::dialect::excel_csv;
+use crate::money::Money;
+use crate::woocsv::OrderDetails;
+
+/// Phrases in the name of a payment method that mean the customer pays the driver, besides the
+/// word `COD`.
+const CASH_ON_DELIVERY: [&str; 4] = ["contrassegno", "alla consegna", "contanti", "cash on delivery"];
+
+/// An order on the delivery sheet of the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestRow {
+    pub order_id: u32,
+    pub customer_name: String,
+    pub address: String,
+    pub postcode: String,
+    pub phone: String,
+    pub packages: usize,
+    pub payment: String,
+    /// The order total if the customer pays on delivery, or else zero.
+    pub to_collect: Money,
+}
+
+/// Whether the customer pays the driver with `payment_gateway`.
+pub fn is_cash_on_delivery(payment_gateway: &str) -> bool {
+    let payment = payment_gateway.to_lowercase();
+    payment.split(|c: char| !c.is_alphanumeric()).any(|word| word == "cod")
+        || CASH_ON_DELIVERY.iter().any(|phrase| payment.contains(phrase))
+}
+
+/// The delivery sheet of `orders`, in the same order, leaving out the orders picked up at the
+/// market.
+pub fn manifest(orders: &[OrderDetails]) -> Vec<ManifestRow> {
+    orders.iter()
+        .filter(|order| !order.is_pickup())
+        .map(|order| ManifestRow {
+            order_id: order.order_id,
+            customer_name: order.customer_name.clone(),
+            address: [&order.shipping_address_line_1, &order.shipping_address_line_2].iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(", "),
+            postcode: order.shipping_postcode.clone(),
+            phone: order.billing_phone_number.clone(),
+            packages: order.packages.len(),
+            payment: order.payment_gateway.clone(),
+            to_collect: if is_cash_on_delivery(&order.payment_gateway) { order.order_total } else { Money::ZERO },
+        })
+        .collect()
+}
+
+/// The delivery sheet as CSV for Excel, see `excel_csv`, with amounts with a decimal comma.
+/// The signature column is left empty, for printing.
+pub fn to_csv(rows: &[ManifestRow]) -> String {
+    let header = ["Ordine", "Cliente", "Indirizzo", "CAP", "Telefono", "Colli", "Pagamento", "Da incassare", "Firma"];
+    excel_csv(header, rows.iter().map(|row| {
+        [
+            row.order_id.to_string(),
+            row.customer_name.clone(),
+            row.address.clone(),
+            row.postcode.clone(),
+            row.phone.clone(),
+            row.packages.to_string(),
+            row.payment.clone(),
+            row.to_collect.to_string(),
+            String::new(),
+        ]
+    }))
+}
+
+#[cfg(test)]
+use crate::woocsv::sample_orders;
+
+#[test]
+fn test_is_cash_on_delivery() {
+    assert!(is_cash_on_delivery("Pagamento alla consegna"));
+    assert!(is_cash_on_delivery("Contrassegno"));
+    assert!(is_cash_on_delivery("COD"));
+    assert!(!is_cash_on_delivery("PayPal o carta di credito"));
+    assert!(!is_cash_on_delivery("Bonifico bancario (codice IBAN)"));
+}
+
+#[test]
+fn test_manifest() {
+    let mut orders = sample_orders();
+    orders[0].payment_gateway = "Pagamento alla consegna".to_owned();
+    let rows = manifest(&orders);
+    // 5357 is picked up at the market.
+    assert_eq!(rows.iter().map(|row| row.order_id).collect::<Vec<_>>(), vec![5358, 11099]);
+    assert_eq!(rows[0].address, "VIA DEI PAZZI 0, SCALA A DESTRA SECONDO PIANO");
+    assert_eq!((rows[0].to_collect, rows[1].to_collect), (Money::from_cents(5710), Money::ZERO));
+    assert_eq!(to_csv(&rows[1..]), "\u{feff}\
+Ordine;Cliente;Indirizzo;CAP;Telefono;Colli;Pagamento;Da incassare;Firma
+11099;gi gu;viao bla 6;20128;3341310000;1;PayPal o Carta di Credito;0,00;
+");
+}
@@ -0,0 +1,284 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// An amount in euros, stored as a whole number of cents so that totals add up exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money {
+    cents: i64,
+}
+
+impl Money {
+    pub const ZERO: Money = Money { cents: 0 };
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money { cents }
+    }
+
+    pub fn euros(euros: i64) -> Self {
+        Money { cents: euros * 100 }
+    }
+
+    /// Rounds to the nearest cent; only meant for amounts that come as JSON numbers.
+    pub fn from_f64(euros: f64) -> Self {
+        Money::from_cents((euros * 100.0).round() as i64)
+    }
+
+    /// Parses amounts as they appear in exports and spreadsheets: `57,10`, `57.10`, `1.234,50`,
+    /// `1,234.50`, `€ 57,10`, `-3`. When both `.` and `,` appear, the last one is the decimal
+    /// separator; a separator that appears more than once separates thousands; a single one is
+    /// the decimal separator, as in the prices of the WooCommerce export (`3.279`). Amounts with
+    /// more than two decimals are rounded to the cent.
+    pub fn parse(s: &str) -> Result<Self, MoneyParseError> {
+        let error = || MoneyParseError(s.to_owned());
+        let cleaned = s.replace("EUR", "")
+            .chars()
+            .filter(|c| *c != '€' && !c.is_whitespace() && *c != '\'')
+            .collect::<String>();
+        let (negative, digits) = match cleaned.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+        };
+
+        let decimal = match (digits.rfind('.'), digits.rfind(',')) {
+            (Some(dot), Some(comma)) => Some(dot.max(comma)),
+            (Some(i), None) if digits.matches('.').count() == 1 => Some(i),
+            (None, Some(i)) if digits.matches(',').count() == 1 => Some(i),
+            _ => None,
+        };
+        let (whole, fraction) = match decimal {
+            Some(i) => (&digits[..i], &digits[i + 1..]),
+            None => (digits, ""),
+        };
+        let thousands = match decimal {
+            Some(i) if digits[i..].starts_with('.') => ',',
+            Some(_) => '.',
+            None => whole.chars().find(|&c| c == '.' || c == ',').unwrap_or(','),
+        };
+        let groups = whole.split(thousands).collect::<Vec<_>>();
+        let well_grouped = groups.len() == 1
+            || (!groups[0].is_empty() && groups[0].len() <= 3 && groups[1..].iter().all(|g| g.len() == 3));
+        let whole = groups.concat();
+        if !well_grouped
+            || (whole.is_empty() && fraction.is_empty())
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(error());
+        }
+
+        let euros: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| error())? };
+        let mut fraction_digits = fraction.chars().map(|c| c as i64 - '0' as i64);
+        let tenths = fraction_digits.next().unwrap_or(0);
+        let hundredths = fraction_digits.next().unwrap_or(0);
+        let round_up = fraction_digits.next().is_some_and(|d| d >= 5);
+        let cents = euros.checked_mul(100).ok_or_else(error)? + tenths * 10 + hundredths + round_up as i64;
+        Ok(Money { cents: if negative { -cents } else { cents } })
+    }
+
+    pub fn abs(self) -> Self {
+        Money { cents: self.cents.abs() }
+    }
+
+    /// `percent`% of the amount, rounded half up to the cent.
+    pub fn percent(self, percent: u32) -> Money {
+        let scaled = self.cents * percent as i64;
+        Money { cents: (scaled + scaled.signum() * 50) / 100 }
+    }
+
+    /// The amount shared in proportion to `weights`, to the cent; what is left over by rounding
+    /// goes to the last share, so that the shares add up to the amount.
+    pub fn split(self, weights: &[u32]) -> Vec<Money> {
+        let total = weights.iter().map(|&w| w as i64).sum::<i64>();
+        if total == 0 {
+            return weights.iter().map(|_| Money::ZERO).collect();
+        }
+        let mut shares = weights.iter().map(|&w| Money { cents: self.cents * w as i64 / total }).collect::<Vec<_>>();
+        let rest = self - shares.iter().sum::<Money>();
+        if let Some(last) = shares.last_mut() {
+            *last += rest;
+        }
+        shares
+    }
+
+    /// The amount with a `.` as decimal separator and no thousands separator, e.g. `1234.50`.
+    pub fn to_plain_string(self) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        format!("{}{}.{:02}", sign, self.cents.abs() / 100, self.cents.abs() % 100)
+    }
+}
+
+/// Italian formatting, e.g. `1.234,50`.
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let euros = (self.cents.abs() / 100).to_string();
+        let mut grouped = String::new();
+        for (i, c) in euros.chars().enumerate() {
+            if i > 0 && i % 3 == euros.len() % 3 {
+                grouped.push('.');
+            }
+            grouped.push(c);
+        }
+        let sign = if self.cents < 0 { "-" } else { "" };
+        write!(f, "{}{},{:02}", sign, grouped, self.cents.abs() % 100)
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Money::parse(s)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money { cents: self.cents + other.cents }
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.cents += other.cents;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money { cents: self.cents - other.cents }
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money { cents: -self.cents }
+    }
+}
+
+impl Mul<u32> for Money {
+    type Output = Money;
+
+    fn mul(self, quantity: u32) -> Money {
+        Money { cents: self.cents * quantity as i64 }
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.cloned().sum()
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_plain_string())
+    }
+}
+
+/// Accepts both strings, which is how WooCommerce sends totals, and numbers, which is how it
+/// sends unit prices.
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MoneyVisitor;
+
+        impl<'de> Visitor<'de> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("an amount in euros")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+                Money::parse(v).map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+                Ok(Money::from_f64(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+                Ok(Money::euros(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+                Ok(Money::euros(v as i64))
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MoneyParseError(String);
+
+impl Display for MoneyParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid amount: {:?}", self.0)
+    }
+}
+
+impl Error for MoneyParseError {}
+
+#[test]
+fn test_parse_money() {
+    assert_eq!(Money::parse("57,10"), Ok(Money::from_cents(5710)));
+    assert_eq!(Money::parse("57.10"), Ok(Money::from_cents(5710)));
+    assert_eq!(Money::parse("3.5"), Ok(Money::from_cents(350)));
+    assert_eq!(Money::parse("40"), Ok(Money::euros(40)));
+    assert_eq!(Money::parse(" € 1.234,50 "), Ok(Money::from_cents(123_450)));
+    assert_eq!(Money::parse("1,234.50€"), Ok(Money::from_cents(123_450)));
+    assert_eq!(Money::parse("1.234.567"), Ok(Money::euros(1_234_567)));
+    assert_eq!(Money::parse("-3,00 EUR"), Ok(Money::euros(-3)));
+    assert_eq!(Money::parse(",5"), Ok(Money::from_cents(50)));
+    assert_eq!(Money::parse("4.125"), Ok(Money::from_cents(413)));
+    assert_eq!(Money::parse("3,279"), Ok(Money::from_cents(328)));
+    assert!(Money::parse("").is_err());
+    assert!(Money::parse("dieci").is_err());
+    assert!(Money::parse("1,2,3.4.5").is_err());
+}
+
+#[test]
+fn test_display_money() {
+    assert_eq!(Money::from_cents(5710).to_string(), "57,10");
+    assert_eq!(Money::euros(5).to_string(), "5,00");
+    assert_eq!(Money::from_cents(123_456_789).to_string(), "1.234.567,89");
+    assert_eq!(Money::from_cents(-5).to_string(), "-0,05");
+    assert_eq!(Money::from_cents(-123_450).to_plain_string(), "-1234.50");
+}
+
+#[test]
+fn test_money_arithmetic() {
+    let prices = [Money::from_cents(10), Money::from_cents(20)];
+    assert_eq!(prices.iter().sum::<Money>(), Money::from_cents(30));
+    assert_eq!(Money::from_cents(170) * 3, Money::from_cents(510));
+    assert_eq!(Money::euros(5) - Money::from_cents(1), Money::from_cents(499));
+    assert_eq!(Money::from_cents(350).percent(10), Money::from_cents(35));
+    assert_eq!(Money::from_cents(125).percent(22), Money::from_cents(28));
+    assert_eq!(Money::from_cents(-125).percent(22), Money::from_cents(-28));
+    assert_eq!(Money::euros(10).split(&[1, 2]), vec![Money::from_cents(333), Money::from_cents(667)]);
+    assert_eq!(Money::euros(10).split(&[0, 0]), vec![Money::ZERO, Money::ZERO]);
+}
+
+#[test]
+fn test_money_serde() {
+    let parsed: Vec<Money> = serde_json::from_str(r#"["57.10", 3.5, 40]"#).unwrap();
+    assert_eq!(parsed, vec![Money::from_cents(5710), Money::from_cents(350), Money::euros(40)]);
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), r#"["57.10","3.50","40.00"]"#);
+}
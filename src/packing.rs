@@ -0,0 +1,441 @@
+use crate::catalogue::ProductIndex;
+use crate::money::Money;
+use crate::woocsv::WooCommerceRow;
+use serde::{Deserialize, Serialize};
+
+pub const PACKING_KEY: &str = "campagnamica.packing";
+
+/// What an order must satisfy for a `PackRule` to apply.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    OrderTotalUpTo(Money),
+    /// Number of distinct products (lines) in the order.
+    ItemCountUpTo(u32),
+    /// Number of units across all lines.
+    TotalQuantityUpTo(u32),
+    /// The order contains a product of this category in the catalogue, or not in the catalogue
+    /// but with this keyword in its name.
+    Category(String),
+    Always,
+}
+
+impl Condition {
+    /// Names of the kinds of condition, in the order used by `kind` and `of_kind`.
+    pub const KINDS: [&'static str; 5] = ["Order total up to (€)", "Products up to", "Units up to", "Contains category", "Always"];
+
+    pub fn kind(&self) -> usize {
+        match self {
+            Condition::OrderTotalUpTo(_) => 0,
+            Condition::ItemCountUpTo(_) => 1,
+            Condition::TotalQuantityUpTo(_) => 2,
+            Condition::Category(_) => 3,
+            Condition::Always => 4,
+        }
+    }
+
+    /// A condition of the given kind, keeping the value of this one where it makes sense.
+    pub fn of_kind(&self, kind: usize) -> Condition {
+        let number = self.value().parse().unwrap_or(0);
+        match kind {
+            0 => Condition::OrderTotalUpTo(Money::parse(&self.value()).unwrap_or_default()),
+            1 => Condition::ItemCountUpTo(number),
+            2 => Condition::TotalQuantityUpTo(number),
+            3 => Condition::Category(String::new()),
+            _ => Condition::Always,
+        }
+    }
+
+    /// The threshold or keyword of the condition, as text for editing.
+    pub fn value(&self) -> String {
+        match self {
+            Condition::OrderTotalUpTo(total) => total.to_string(),
+            Condition::ItemCountUpTo(n) | Condition::TotalQuantityUpTo(n) => n.to_string(),
+            Condition::Category(keyword) => keyword.clone(),
+            Condition::Always => String::new(),
+        }
+    }
+
+    /// This condition with its threshold or keyword replaced by `value`, if valid.
+    pub fn with_value(&self, value: &str) -> Option<Condition> {
+        Some(match self {
+            Condition::OrderTotalUpTo(_) => Condition::OrderTotalUpTo(Money::parse(value).ok()?),
+            Condition::ItemCountUpTo(_) => Condition::ItemCountUpTo(value.trim().parse().ok()?),
+            Condition::TotalQuantityUpTo(_) => Condition::TotalQuantityUpTo(value.trim().parse().ok()?),
+            Condition::Category(_) => Condition::Category(value.trim().to_owned()),
+            Condition::Always => Condition::Always,
+        })
+    }
+
+    fn matches(&self, rows: &[&WooCommerceRow], products: &ProductIndex) -> bool {
+        match self {
+            Condition::OrderTotalUpTo(max) => rows.first().into_iter().all(|row| row.order_total <= *max),
+            Condition::ItemCountUpTo(max) => rows.len() as u32 <= *max,
+            Condition::TotalQuantityUpTo(max) => rows.iter().map(|row| row.quantity).sum::<u32>() <= *max,
+            Condition::Category(keyword) => {
+                let keyword = keyword.to_lowercase();
+                !keyword.is_empty() && rows.iter().any(|row| match products.product(row) {
+                    Some(product) => product.category.to_lowercase() == keyword,
+                    None => row.product_name.to_lowercase().contains(&keyword),
+                })
+            }
+            Condition::Always => true,
+        }
+    }
+}
+
+/// Orders that satisfy `condition` are split into `packages` packages.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PackRule {
+    pub condition: Condition,
+    pub packages: u32,
+}
+
+/// How the items of an order are split into packages.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PackingOptions {
+    pub multipack: bool,
+    /// Tried in order; the first rule that matches gives the number of packages.
+    pub rules: Vec<PackRule>,
+    /// Grams a package may hold; with a limit on weight or volume, items are spread over as
+    /// many packages as needed to stay within it, using the weights and volumes in the catalogue.
+    #[serde(default)]
+    pub max_weight: Option<u32>,
+    /// Millilitres a package may hold.
+    #[serde(default)]
+    pub max_volume: Option<u32>,
+    /// Whether a line may be divided between packages, e.g. 12 bottles of milk into 6 + 6.
+    #[serde(default)]
+    pub split_lines: bool,
+}
+
+/// The part of an order line that goes into one package.
+#[derive(Clone, Copy, Debug)]
+pub struct Portion<'a> {
+    pub row: &'a WooCommerceRow,
+    pub quantity: u32,
+}
+
+impl PackingOptions {
+    /// The number of packages for the rows of one order: the one given by the first matching
+    /// rule, or 1 if none matches or multi-pack is off.
+    pub fn package_count(&self, rows: &[&WooCommerceRow], products: &ProductIndex) -> u32 {
+        if !self.multipack {
+            return 1;
+        }
+        self.rules.iter()
+            .find(|rule| rule.condition.matches(rows, products))
+            .map_or(1, |rule| rule.packages.max(1))
+    }
+
+    /// Splits the rows of one order into packages, coldest first and products with no temperature
+    /// class last. Products of different temperature classes always go in separate packages, even
+    /// with multi-pack off; the packages given by the rules are shared between the classes by
+    /// number of lines.
+    pub fn split<'a>(&self, rows: Vec<&'a WooCommerceRow>, products: &ProductIndex) -> Vec<Vec<Portion<'a>>> {
+        use itertools::Itertools;
+
+        let num_packages = self.package_count(&rows, products) as usize;
+        let classes = rows.into_iter()
+            .into_group_map_by(|row| products.temperature(row))
+            .into_iter()
+            .sorted_by_key(|(temperature, _)| (temperature.is_none(), *temperature))
+            .map(|(_, rows)| rows)
+            .collect::<Vec<_>>();
+        let shares = share_packages(num_packages, &classes.iter().map(Vec::len).collect::<Vec<_>>());
+        classes.into_iter()
+            .zip(shares)
+            .flat_map(|(rows, num_packages)| self.split_class(rows, num_packages, products))
+            .collect()
+    }
+
+    /// Splits rows of one temperature class into packages. Without limits on weight or volume,
+    /// the rows are split evenly by count into `num_packages`; with limits, that number is the
+    /// least, and more packages are added when the items do not fit, even with multi-pack off.
+    /// With `split_lines`, a line is divided between packages when it does not fit whole, and
+    /// without limits the packages of multi-pack are balanced by number of units.
+    fn split_class<'a>(&self, rows: Vec<&'a WooCommerceRow>, num_packages: usize, products: &ProductIndex) -> Vec<Vec<Portion<'a>>> {
+        use itertools::Itertools;
+
+        let rows = rows.into_iter().sorted_by_key(|row| &row.product_name).collect::<Vec<_>>();
+        let limited = self.max_weight.is_some() || self.max_volume.is_some();
+        if !limited && (!self.multipack || !self.split_lines) {
+            let items_per_package = calculate_items_per_package(rows.len(), num_packages);
+            return rows.chunks(items_per_package)
+                .map(|chunk| chunk.iter().map(|row| Portion { row, quantity: row.quantity }).collect())
+                .collect();
+        }
+
+        let unit_size = |row: &WooCommerceRow| products.product(row).map_or((0, 0), |product| (product.weight, product.volume));
+        let units = rows.iter().map(|row| row.quantity).sum::<u32>();
+        let target_units = if limited { None } else { Some(units / num_packages as u32 + u32::from(units % num_packages as u32 > 0)) };
+        // How many units of a row with the given unit weight and volume still fit in a package.
+        let room = |p: &Package, (weight, volume): (u32, u32)| {
+            let left = |limit: Option<u32>, used: u32, size: u32| match limit {
+                Some(limit) if size > 0 => limit.saturating_sub(used) / size,
+                _ => u32::MAX,
+            };
+            let room = left(self.max_weight, p.weight, weight)
+                .min(left(self.max_volume, p.volume, volume))
+                .min(target_units.map_or(u32::MAX, |target| target.saturating_sub(p.units)));
+            if p.portions.is_empty() { room.max(1) } else { room }
+        };
+
+        let mut packages: Vec<Package> = (0..num_packages).map(|_| Package::default()).collect();
+        let line_size = |row: &WooCommerceRow| {
+            let (weight, volume) = unit_size(row);
+            (weight * row.quantity, volume * row.quantity)
+        };
+        for row in rows.iter().copied().sorted_by_key(|row| std::cmp::Reverse(line_size(row))) {
+            let size = unit_size(row);
+            let mut left = row.quantity;
+            loop {
+                let needed = if self.split_lines { 1 } else { left };
+                let lightest = packages.iter_mut()
+                    .filter(|p| p.portions.is_empty() || room(p, size) >= needed)
+                    .min_by_key(|p| (p.weight, p.volume, p.units));
+                let package = match lightest {
+                    Some(package) => package,
+                    None => {
+                        packages.push(Package::default());
+                        packages.last_mut().unwrap()
+                    }
+                };
+                let quantity = if self.split_lines { left.min(room(package, size)) } else { left };
+                package.portions.push(Portion { row, quantity });
+                package.weight += size.0 * quantity;
+                package.volume += size.1 * quantity;
+                package.units += quantity;
+                left -= quantity;
+                if left == 0 {
+                    break;
+                }
+            }
+        }
+
+        packages.into_iter()
+            .filter(|p| !p.portions.is_empty())
+            .map(|p| p.portions.into_iter().sorted_by_key(|portion| &portion.row.product_name).collect())
+            .collect()
+    }
+
+    pub fn default_rules() -> Vec<PackRule> {
+        vec![
+            PackRule { condition: Condition::OrderTotalUpTo(Money::euros(40)), packages: 1 },
+            PackRule { condition: Condition::OrderTotalUpTo(Money::euros(70)), packages: 2 },
+            PackRule { condition: Condition::OrderTotalUpTo(Money::euros(80)), packages: 3 },
+            PackRule { condition: Condition::Always, packages: 4 },
+        ]
+    }
+}
+
+impl Default for PackingOptions {
+    fn default() -> Self {
+        PackingOptions { multipack: true, rules: PackingOptions::default_rules(), max_weight: None, max_volume: None, split_lines: false }
+    }
+}
+
+#[derive(Default)]
+struct Package<'a> {
+    portions: Vec<Portion<'a>>,
+    weight: u32,
+    volume: u32,
+    units: u32,
+}
+
+/// Shares `num_packages` between groups of `sizes` items: at least one package each, and the
+/// others one by one to the group with the most items per package.
+fn share_packages(num_packages: usize, sizes: &[usize]) -> Vec<usize> {
+    let mut shares = vec![1; sizes.len()];
+    for _ in sizes.len()..num_packages {
+        let fullest = (0..sizes.len()).max_by(|&a, &b| (sizes[a] * shares[b]).cmp(&(sizes[b] * shares[a])).then(b.cmp(&a)));
+        match fullest {
+            Some(i) => shares[i] += 1,
+            None => break,
+        }
+    }
+    shares
+}
+
+fn calculate_items_per_package(num_rows: usize, num_packages: usize) -> usize {
+    let (mut items_per_package, remainder) = (num_rows / num_packages, num_rows % num_packages);
+    if remainder > 0 {
+        items_per_package += 1;
+    }
+    items_per_package.max(1)
+}
+
+#[cfg(test)]
+use crate::catalogue::Catalogue;
+
+#[cfg(test)]
+fn row(product_name: &str, quantity: u32, order_total: Money) -> WooCommerceRow {
+    crate::woocsv::WooCommerceRowBuilder::default()
+        .order_id(1)
+        .order_date(String::new())
+        .customer_name(String::new())
+        .order_total(order_total)
+        .order_shipping(Money::ZERO)
+        .payment_gateway(String::new())
+        .shipping_method(String::new())
+        .shipping_address_line_1(String::new())
+        .shipping_address_line_2(String::new())
+        .shipping_postcode(String::new())
+        .billing_phone_number(String::new())
+        ._transaction_id(String::new())
+        .product_name(product_name.to_owned())
+        .sku(String::new())
+        .quantity(quantity)
+        .item_price(Money::ZERO)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_default_rules_match_old_bands() {
+    let options = PackingOptions::default();
+    let catalogue = Catalogue::default();
+    let bread = |euros| row("PANE", 1, Money::euros(euros));
+    let count = |euros| options.package_count(&[&bread(euros)], &catalogue.index(&[bread(euros)]));
+    assert_eq!(count(40), 1);
+    assert_eq!(count(41), 2);
+    assert_eq!(count(75), 3);
+    assert_eq!(count(200), 4);
+
+    let single = PackingOptions { multipack: false, ..PackingOptions::default() };
+    assert_eq!(single.package_count(&[&bread(200)], &catalogue.index(&[bread(200)])), 1);
+}
+
+#[test]
+fn test_first_matching_rule_wins() {
+    let options = PackingOptions {
+        rules: vec![
+            PackRule { condition: Condition::Category("surgelat".to_owned()), packages: 2 },
+            PackRule { condition: Condition::TotalQuantityUpTo(6), packages: 1 },
+            PackRule { condition: Condition::ItemCountUpTo(1), packages: 3 },
+        ],
+        ..PackingOptions::default()
+    };
+    let frozen = row("PISELLI SURGELATI", 1, Money::euros(5));
+    let milk = row("LATTE", 12, Money::euros(20));
+    let bread = row("PANE", 2, Money::euros(20));
+    let peas = row("PISELLI FINI", 1, Money::euros(5));
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text("Name;Category\nPISELLI FINI;surgelati").unwrap();
+    let products = catalogue.index(vec![&frozen, &milk, &bread, &peas]);
+    assert_eq!(options.package_count(&[&bread, &frozen], &products), 2);
+    assert_eq!(options.package_count(&[&bread], &products), 1);
+    assert_eq!(options.package_count(&[&milk], &products), 3);
+    assert_eq!(options.package_count(&[&milk, &bread], &products), 1);
+
+    let options = PackingOptions { rules: vec![PackRule { condition: Condition::Category("SURGELATI".to_owned()), packages: 2 }], ..options };
+    assert_eq!(options.package_count(&[&peas], &products), 2);
+}
+
+#[test]
+fn test_edit_conditions() {
+    let condition = Condition::ItemCountUpTo(3);
+    assert_eq!(condition.of_kind(2), Condition::TotalQuantityUpTo(3));
+    assert_eq!(condition.of_kind(0), Condition::OrderTotalUpTo(Money::euros(3)));
+    assert_eq!(Condition::OrderTotalUpTo(Money::ZERO).with_value("45,50"), Some(Condition::OrderTotalUpTo(Money::from_cents(4550))));
+    assert_eq!(condition.with_value("tre"), None);
+}
+
+#[test]
+fn test_split_by_weight() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text("Name;Weight;Volume\nPATATE;5;8\nMIELE;0,5;0,4\nFARINA;1;1,5").unwrap();
+    let options = PackingOptions {
+        rules: vec![PackRule { condition: Condition::Always, packages: 1 }],
+        max_weight: Some(10_000),
+        ..PackingOptions::default()
+    };
+    let potatoes = row("PATATE", 3, Money::euros(30));
+    let honey = row("MIELE", 2, Money::euros(30));
+    let flour = row("FARINA", 4, Money::euros(30));
+    let unknown = row("UOVA", 6, Money::euros(30));
+
+    let products = catalogue.index(vec![&potatoes, &honey, &flour, &unknown]);
+    let packages = options.split(vec![&potatoes, &honey, &flour, &unknown], &products);
+    let names = packages.iter()
+        .map(|p| p.iter().map(|portion| portion.row.product_name.as_str()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    // 15 kg of potatoes cannot fit in a 10 kg box, so they go alone in an overweight one.
+    assert_eq!(names, vec![vec!["PATATE"], vec!["FARINA", "MIELE", "UOVA"]]);
+
+    let options = PackingOptions { max_weight: None, max_volume: Some(5_000), ..options };
+    let packages = options.split(vec![&honey, &flour, &unknown], &products);
+    assert_eq!(packages.len(), 2);
+
+    // Without multi-pack, the limits still apply, starting from a single package.
+    let options = PackingOptions { multipack: false, max_weight: Some(10_000), max_volume: None, ..options };
+    let packages = options.split(vec![&potatoes, &honey, &flour, &unknown], &products);
+    assert_eq!(packages.len(), 2);
+    let options = PackingOptions { max_weight: None, ..options };
+    assert_eq!(options.split(vec![&potatoes, &honey, &flour, &unknown], &products).len(), 1);
+}
+
+#[test]
+fn test_split_lines() {
+    let options = PackingOptions {
+        rules: vec![PackRule { condition: Condition::Always, packages: 2 }],
+        split_lines: true,
+        ..PackingOptions::default()
+    };
+    let milk = row("LATTE", 12, Money::euros(30));
+    let bread = row("PANE", 1, Money::euros(30));
+    let quantities = |packages: Vec<Vec<Portion>>| packages.iter()
+        .map(|p| p.iter().map(|portion| (portion.row.product_name.clone(), portion.quantity)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let catalogue = Catalogue::default();
+    let products = catalogue.index(vec![&milk, &bread]);
+    assert_eq!(quantities(options.split(vec![&milk], &products)), vec![
+        vec![("LATTE".to_owned(), 6)],
+        vec![("LATTE".to_owned(), 6)],
+    ]);
+    assert_eq!(quantities(options.split(vec![&bread, &milk], &products)), vec![
+        vec![("LATTE".to_owned(), 7)],
+        vec![("LATTE".to_owned(), 5), ("PANE".to_owned(), 1)],
+    ]);
+
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text("Name;Weight\nLATTE;1").unwrap();
+    let options = PackingOptions { max_weight: Some(5000), ..options };
+    let packages = options.split(vec![&milk], &catalogue.index(vec![&milk]));
+    assert_eq!(packages.iter().map(|p| p[0].quantity).collect::<Vec<_>>(), vec![5, 5, 2]);
+}
+
+#[test]
+fn test_split_by_temperature() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_temperature_rules_text("GALLETTO: frozen\nYOGURT: chilled\nPANE: ambient\nFARINA: ambient");
+    let options = PackingOptions { multipack: false, ..PackingOptions::default() };
+    let chicken = row("GALLETTO", 1, Money::euros(100));
+    let yogurt = row("YOGURT", 1, Money::euros(100));
+    let bread = row("PANE", 1, Money::euros(100));
+    let flour = row("FARINA", 1, Money::euros(100));
+    let names = |packages: Vec<Vec<Portion>>| packages.iter()
+        .map(|p| p.iter().map(|portion| portion.row.product_name.as_str()).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>();
+
+    let products = catalogue.index(vec![&chicken, &yogurt, &bread, &flour]);
+
+    assert_eq!(names(options.split(vec![&bread, &yogurt, &chicken, &flour], &products)), vec!["GALLETTO", "YOGURT", "FARINA,PANE"]);
+    // Four packages by the rules: the extra one goes to the ambient products, which have more lines.
+    let options = PackingOptions { multipack: true, ..options };
+    assert_eq!(names(options.split(vec![&bread, &yogurt, &chicken, &flour], &products)), vec!["GALLETTO", "YOGURT", "FARINA", "PANE"]);
+
+    // The catalogue wins over the rules.
+    catalogue.set_text("Name;Temperature\nYOGURT;ambient").unwrap();
+    let products = catalogue.index(vec![&chicken, &yogurt, &bread, &flour]);
+    assert_eq!(names(options.split(vec![&bread, &yogurt, &chicken, &flour], &products)), vec!["GALLETTO", "FARINA", "PANE", "YOGURT"]);
+
+    // Products with no temperature class go last, by themselves.
+    let honey = row("MIELE", 1, Money::euros(100));
+    let products = catalogue.index(vec![&chicken, &honey, &bread]);
+    assert_eq!(products.unclassified(), vec!["MIELE"]);
+    let options = PackingOptions { multipack: false, ..options };
+    assert_eq!(names(options.split(vec![&honey, &bread, &chicken], &products)), vec!["GALLETTO", "PANE", "MIELE"]);
+    assert_eq!(share_packages(1, &[]), Vec::<usize>::new());
+    assert_eq!(share_packages(5, &[1, 6]), vec![1, 4]);
+}
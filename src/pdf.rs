@@ -0,0 +1,394 @@
+use anyhow::{anyhow, Result};
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+/// Points in a millimetre; PDF measures everything in points.
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// The characters of the Windows-1252 codes 0x80 to 0x9F, which differ from Latin-1; `None` for
+/// the unused codes.
+const WIN_ANSI_80_9F: [Option<char>; 32] = [
+    Some('€'), None, Some('‚'), Some('ƒ'), Some('„'), Some('…'), Some('†'), Some('‡'),
+    Some('ˆ'), Some('‰'), Some('Š'), Some('‹'), Some('Œ'), None, Some('Ž'), None,
+    None, Some('‘'), Some('’'), Some('“'), Some('”'), Some('•'), Some('–'), Some('—'),
+    Some('˜'), Some('™'), Some('š'), Some('›'), Some('œ'), None, Some('ž'), Some('Ÿ'),
+];
+
+/// The character of the code `code` of the WinAnsi encoding of PDF, which covers all the
+/// accented letters of Italian and `€`.
+fn win_ansi_char(code: u8) -> Option<char> {
+    match code {
+        0x20..=0x7E | 0xA0..=0xFF => Some(code as char),
+        0x80..=0x9F => WIN_ANSI_80_9F[usize::from(code - 0x80)],
+        _ => None,
+    }
+}
+
+/// `text` in the WinAnsi encoding; a few common characters outside it are spelled out, the others
+/// become `?`.
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for c in text.chars() {
+        match c {
+            '≥' => bytes.extend_from_slice(b">="),
+            '≤' => bytes.extend_from_slice(b"<="),
+            '\t' | '\n' | '\r' => bytes.push(b' '),
+            _ => bytes.push((0x20..=0xFF).find(|&code| win_ansi_char(code) == Some(c)).unwrap_or(b'?')),
+        }
+    }
+    bytes
+}
+
+/// A TrueType font, embedded whole in the documents that use it, so it should only have the
+/// glyphs of WinAnsi (see `fonts/subset.py`).
+pub struct Font {
+    name: &'static str,
+    data: &'static [u8],
+    /// `data` compressed for `FontFile2`, once for all the documents.
+    compressed: Vec<u8>,
+    /// Advance of the codes 32 to 255, in thousandths of the font size.
+    widths: Vec<u32>,
+    bbox: [i32; 4],
+    ascent: i32,
+    descent: i32,
+    cap_height: i32,
+}
+
+impl Font {
+    /// Reads the metrics of the TrueType font `data`; `name` is its PostScript name, without
+    /// spaces.
+    pub fn parse(name: &'static str, data: &'static [u8]) -> Result<Font> {
+        let tables = TrueType { data };
+        let head = tables.table(b"head")?;
+        let hhea = tables.table(b"hhea")?;
+        let hmtx = tables.table(b"hmtx")?;
+        let units = f64::from(read_u16(head, 18)?);
+        let scale = |value: i16| (f64::from(value) * 1000.0 / units).round() as i32;
+        let metrics = usize::from(read_u16(hhea, 34)?);
+        let advance = |glyph: u16| -> Result<u16> { read_u16(hmtx, 4 * usize::from(glyph).min(metrics - 1)) };
+        let cmap = tables.cmap()?;
+        let widths = (32..=255u8)
+            .map(|code| {
+                let glyph = win_ansi_char(code).map_or(Ok(0), |c| glyph_index(cmap, c))?;
+                Ok(scale(advance(glyph)? as i16).max(0) as u32)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let ascent = scale(read_u16(hhea, 4)? as i16);
+        let cap_height = tables.table(b"OS/2").ok()
+            .filter(|os2| read_u16(os2, 0).is_ok_and(|version| version >= 2))
+            .and_then(|os2| read_u16(os2, 88).ok())
+            .map_or(ascent, |height| scale(height as i16));
+        Ok(Font {
+            name,
+            data,
+            compressed: miniz_oxide::deflate::compress_to_vec_zlib(data, 6),
+            widths,
+            bbox: [scale(read_u16(head, 36)? as i16), scale(read_u16(head, 38)? as i16), scale(read_u16(head, 40)? as i16), scale(read_u16(head, 42)? as i16)],
+            ascent,
+            descent: scale(read_u16(hhea, 6)? as i16),
+            cap_height,
+        })
+    }
+
+    /// The width of `text` at `size` points, in millimetres.
+    pub fn width(&self, text: &str, size: f64) -> f64 {
+        let thousandths: u32 = encode(text).iter().map(|&code| self.widths[usize::from(code.max(32)) - 32]).sum();
+        f64::from(thousandths) * size / 1000.0 / POINTS_PER_MM
+    }
+
+    /// `text` split into lines at most `width` millimetres wide at `size` points, breaking
+    /// between words; words too long for a line are cut with `…`.
+    pub fn wrap(&self, text: &str, size: f64, width: f64) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for word in text.split_whitespace() {
+            match lines.last_mut() {
+                Some(line) if self.width(&format!("{} {}", line, word), size) <= width => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                _ => lines.push(self.truncate(word, size, width)),
+            }
+        }
+        lines
+    }
+
+    /// `text`, cut with `…` if wider than `width` millimetres at `size` points.
+    pub fn truncate(&self, text: &str, size: f64, width: f64) -> String {
+        if self.width(text, size) <= width {
+            return text.to_owned();
+        }
+        let mut short = text.to_owned();
+        while !short.is_empty() && self.width(&format!("{}…", short), size) > width {
+            short.pop();
+        }
+        format!("{}…", short.trim_end())
+    }
+}
+
+/// The tables of a TrueType font.
+struct TrueType {
+    data: &'static [u8],
+}
+
+impl TrueType {
+    fn table(&self, tag: &[u8; 4]) -> Result<&'static [u8]> {
+        let count = usize::from(read_u16(self.data, 4)?);
+        for i in 0..count {
+            let record = 12 + 16 * i;
+            if self.data.get(record..record + 4) == Some(&tag[..]) {
+                let offset = read_u32(self.data, record + 8)? as usize;
+                let length = read_u32(self.data, record + 12)? as usize;
+                return self.data.get(offset..offset + length).ok_or_else(|| anyhow!("truncated font"));
+            }
+        }
+        Err(anyhow!("no {} table in the font", String::from_utf8_lossy(tag)))
+    }
+
+    /// The format 4 subtable of `cmap` for Unicode.
+    fn cmap(&self) -> Result<&'static [u8]> {
+        let cmap = self.table(b"cmap")?;
+        for i in 0..usize::from(read_u16(cmap, 2)?) {
+            let record = 4 + 8 * i;
+            let (platform, encoding) = (read_u16(cmap, record)?, read_u16(cmap, record + 2)?);
+            let subtable = cmap.get(read_u32(cmap, record + 4)? as usize..).ok_or_else(|| anyhow!("truncated font"))?;
+            if (platform == 3 && encoding == 1 || platform == 0) && read_u16(subtable, 0)? == 4 {
+                return Ok(subtable);
+            }
+        }
+        Err(anyhow!("no Unicode character map in the font"))
+    }
+}
+
+/// The glyph of `c` in the format 4 character map `cmap`, or 0 (the missing glyph).
+fn glyph_index(cmap: &[u8], c: char) -> Result<u16> {
+    let code = match u16::try_from(u32::from(c)) {
+        Ok(code) => code,
+        Err(_) => return Ok(0),
+    };
+    let segments = usize::from(read_u16(cmap, 6)? / 2);
+    let ends = 14;
+    let starts = ends + 2 * segments + 2;
+    let deltas = starts + 2 * segments;
+    let range_offsets = deltas + 2 * segments;
+    for i in 0..segments {
+        if read_u16(cmap, ends + 2 * i)? < code {
+            continue;
+        }
+        let start = read_u16(cmap, starts + 2 * i)?;
+        if start > code {
+            return Ok(0);
+        }
+        let delta = read_u16(cmap, deltas + 2 * i)?;
+        let range_offset = usize::from(read_u16(cmap, range_offsets + 2 * i)?);
+        if range_offset == 0 {
+            return Ok(code.wrapping_add(delta));
+        }
+        let glyph = read_u16(cmap, range_offsets + 2 * i + range_offset + 2 * usize::from(code - start))?;
+        return Ok(if glyph == 0 { 0 } else { glyph.wrapping_add(delta) });
+    }
+    Ok(0)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| anyhow!("truncated font"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| anyhow!("truncated font"))
+}
+
+/// A page being drawn. Positions are in millimetres from the top left corner, and the position
+/// of text is the left end of its baseline.
+pub struct Page {
+    width: f64,
+    height: f64,
+    content: String,
+}
+
+impl Page {
+    pub fn new(width: f64, height: f64) -> Page {
+        Page { width, height, content: String::new() }
+    }
+
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * POINTS_PER_MM, (self.height - y) * POINTS_PER_MM)
+    }
+
+    /// Writes `text` with the `font`-th font of the document, at `size` points.
+    pub fn text(&mut self, x: f64, y: f64, font: usize, size: f64, text: &str) {
+        let (x, y) = self.point(x, y);
+        let hex = encode(text).iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+        writeln!(self.content, "BT /F{} {:.1} Tf {:.2} {:.2} Td <{}> Tj ET", font + 1, size, x, y, hex).expect("writing to a string");
+    }
+
+    /// Sets the gray of what is drawn next, from 0 (black) to 1 (white).
+    pub fn gray(&mut self, gray: f64) {
+        writeln!(self.content, "{:.2} g {:.2} G", gray, gray).expect("writing to a string");
+    }
+
+    /// Draws a line `thickness` millimetres thick.
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, thickness: f64) {
+        let ((x1, y1), (x2, y2)) = (self.point(x1, y1), self.point(x2, y2));
+        writeln!(self.content, "{:.2} w {:.2} {:.2} m {:.2} {:.2} l S", thickness * POINTS_PER_MM, x1, y1, x2, y2).expect("writing to a string");
+    }
+
+    /// Draws the outline of a rectangle, `thickness` millimetres thick, or fills it if
+    /// `thickness` is zero.
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, thickness: f64) {
+        let (x, y) = self.point(x, y + height);
+        let (width, height) = (width * POINTS_PER_MM, height * POINTS_PER_MM);
+        if thickness > 0.0 {
+            writeln!(self.content, "{:.2} w {:.2} {:.2} {:.2} {:.2} re S", thickness * POINTS_PER_MM, x, y, width, height).expect("writing to a string");
+        } else {
+            writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} re f", x, y, width, height).expect("writing to a string");
+        }
+    }
+}
+
+/// A PDF document with embedded fonts, built page by page.
+pub struct Document<'f> {
+    fonts: Vec<&'f Font>,
+    pages: Vec<Page>,
+}
+
+impl<'f> Document<'f> {
+    /// A document whose text uses `fonts`, numbered from 0 in `Page::text`.
+    pub fn new(fonts: Vec<&'f Font>) -> Document<'f> {
+        Document { fonts, pages: Vec::new() }
+    }
+
+    pub fn add_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ObjectWriter { bytes: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(), offsets: Vec::new() };
+        // Objects 1 and 2 are the catalogue and the page tree, then three for each font and two
+        // for each page.
+        let first_page = 3 + 3 * self.fonts.len();
+        writer.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+        let kids = (0..self.pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect::<Vec<_>>().join(" ");
+        writer.object(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, self.pages.len()).as_bytes());
+        for (i, font) in self.fonts.iter().enumerate() {
+            let id = 3 + 3 * i;
+            let widths = font.widths.iter().map(u32::to_string).collect::<Vec<_>>().join(" ");
+            writer.object(format!(
+                "<< /Type /Font /Subtype /TrueType /BaseFont /{} /FirstChar 32 /LastChar 255 /Widths [{}] /Encoding /WinAnsiEncoding /FontDescriptor {} 0 R >>",
+                font.name, widths, id + 1,
+            ).as_bytes());
+            writer.object(format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {} 0 R >>",
+                font.name, font.bbox[0], font.bbox[1], font.bbox[2], font.bbox[3], font.ascent, font.descent, font.cap_height, id + 2,
+            ).as_bytes());
+            writer.stream(&format!("/Length1 {} /Filter /FlateDecode", font.data.len()), &font.compressed);
+        }
+        let fonts = (0..self.fonts.len()).map(|i| format!("/F{} {} 0 R", i + 1, 3 + 3 * i)).collect::<Vec<_>>().join(" ");
+        for (i, page) in self.pages.iter().enumerate() {
+            let (width, height) = (page.width * POINTS_PER_MM, page.height * POINTS_PER_MM);
+            writer.object(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+                width, height, fonts, first_page + 2 * i + 1,
+            ).as_bytes());
+            writer.stream("", page.content.as_bytes());
+        }
+        writer.finish()
+    }
+}
+
+/// Writes numbered objects, remembering where each starts for the cross-reference table.
+struct ObjectWriter {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl ObjectWriter {
+    fn object(&mut self, body: &[u8]) {
+        self.offsets.push(self.bytes.len());
+        self.bytes.extend_from_slice(format!("{} 0 obj\n", self.offsets.len()).as_bytes());
+        self.bytes.extend_from_slice(body);
+        self.bytes.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, entries: &str, data: &[u8]) {
+        let mut body = format!("<< /Length {} {}>>\nstream\n", data.len(), if entries.is_empty() { String::new() } else { format!("{} ", entries) }).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(&body);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.bytes.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", self.offsets.len() + 1, xref));
+        self.bytes.extend_from_slice(table.as_bytes());
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+fn regular() -> Font {
+    Font::parse("DejaVuSans", include_bytes!("../fonts/DejaVuSans-WinAnsi.ttf")).unwrap()
+}
+
+#[test]
+fn test_encode() {
+    assert_eq!(encode("Ragù 3,50 €"), b"Rag\xF9 3,50 \x80");
+    assert_eq!(encode("È perché ≥ 1 kg ✓"), b"\xC8 perch\xE9 >= 1 kg ?");
+}
+
+#[test]
+fn test_font_metrics() {
+    let font = regular();
+    // Every character of Italian has a glyph, as wide as the same letter without the accent.
+    for &(accented, plain) in &[('à', 'a'), ('è', 'e'), ('é', 'e'), ('ì', 'i'), ('ò', 'o'), ('ù', 'u'), ('È', 'E')] {
+        assert_eq!(font.width(&accented.to_string(), 10.0), font.width(&plain.to_string(), 10.0));
+    }
+    assert!(font.width("W", 10.0) > font.width("i", 10.0));
+    assert!(font.width("€", 10.0) > 0.0);
+    let lines = font.wrap("CARNE TRITA DI MANZO PER RAGU' E POLPETTE", 10.0, 40.0);
+    assert!(lines.len() > 1 && lines.iter().all(|line| font.width(line, 10.0) <= 40.0));
+    assert_eq!(lines.join(" "), "CARNE TRITA DI MANZO PER RAGU' E POLPETTE");
+    assert!(font.truncate("PRECIPITEVOLISSIMEVOLMENTE", 10.0, 20.0).ends_with('…'));
+}
+
+#[test]
+fn test_subset_fonts() {
+    // The subsets have the same metrics as the whole fonts, for every code.
+    for &(whole, subset) in &[
+        (&include_bytes!("../fonts/DejaVuSans.ttf")[..], &include_bytes!("../fonts/DejaVuSans-WinAnsi.ttf")[..]),
+        (&include_bytes!("../fonts/DejaVuSans-Bold.ttf")[..], &include_bytes!("../fonts/DejaVuSans-Bold-WinAnsi.ttf")[..]),
+    ] {
+        let (whole, subset) = (Font::parse("Whole", whole).unwrap(), Font::parse("Subset", subset).unwrap());
+        assert_eq!(whole.widths, subset.widths);
+        assert_eq!((whole.bbox, whole.ascent, whole.descent), (subset.bbox, subset.ascent, subset.descent));
+        assert!(subset.data.len() < 64 * 1024);
+    }
+}
+
+#[test]
+fn test_document() {
+    let font = regular();
+    let mut document = Document::new(vec![&font]);
+    let mut page = Page::new(105.0, 148.0);
+    page.text(5.0, 10.0, 0, 12.0, "Città");
+    document.add_page(page);
+    let bytes = document.to_bytes();
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/MediaBox [0 0 297.64 419.53]"));
+    assert!(text.contains("<43697474E0> Tj"));
+    // The cross-reference table points at the start of each object.
+    let xref = text[text.rfind("startxref").unwrap() + 10..].lines().next().unwrap().parse::<usize>().unwrap();
+    let table = String::from_utf8_lossy(&bytes[xref..]);
+    for (i, line) in table.lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+        let offset = line[..10].parse::<usize>().unwrap();
+        assert!(bytes[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+    }
+}
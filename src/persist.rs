@@ -0,0 +1,19 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use yew::format::Json;
+use yew::services::storage::{Area, StorageService};
+
+/// Reads a setting saved by `save` from the browser's localStorage. Missing or unreadable
+/// settings yield `None`, so that callers can fall back to their defaults.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let storage = StorageService::new(Area::Local).ok()?;
+    let Json(value): Json<anyhow::Result<T>> = storage.restore(key);
+    value.ok()
+}
+
+/// Saves a setting to the browser's localStorage, so that it survives a reload of the page.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    if let Ok(mut storage) = StorageService::new(Area::Local) {
+        storage.store(key, Json(value));
+    }
+}
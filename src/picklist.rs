@@ -0,0 +1,196 @@
+use crate::bundles::BundleComponent;
+use crate::catalogue::{Catalogue, Product};
+use crate::woocsv::WooCommerceRow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What the pick list groups the products by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PickGroupBy {
+    #[default]
+    Producer,
+    Location,
+}
+
+/// A zone for the products whose name contains `keyword` (case-insensitive), when the catalogue
+/// does not say.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZoneRule {
+    pub keyword: String,
+    pub zone: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PickListOptions {
+    pub group_by: PickGroupBy,
+    /// The zones in the order the pickers walk past them. A zone also stands for the zones that
+    /// start with it, so that `A` covers the shelves `A1`, `A2`...
+    pub walk_path: Vec<String>,
+    pub zone_rules: Vec<ZoneRule>,
+}
+
+impl PickListOptions {
+    /// The zone of a product: its producer or its location in the catalogue, or else the zone of
+    /// the first rule whose keyword `product_name` contains, or else empty.
+    pub fn zone(&self, product: Option<&Product>, product_name: &str) -> String {
+        let zone = product.map_or("", |product| match self.group_by {
+            PickGroupBy::Producer => &product.producer,
+            PickGroupBy::Location => &product.location,
+        });
+        if !zone.trim().is_empty() {
+            return zone.trim().to_owned();
+        }
+        let name = product_name.to_lowercase();
+        self.zone_rules.iter()
+            .find(|rule| name.contains(&rule.keyword.to_lowercase()))
+            .map_or_else(String::new, |rule| rule.zone.clone())
+    }
+
+    /// The position of `zone` along the walk path: the one of the same zone, or else the one of
+    /// the longest zone it starts with, ignoring case.
+    fn stop(&self, zone: &str) -> Option<usize> {
+        let zone = zone.to_lowercase();
+        let stops = self.walk_path.iter().map(|stop| stop.to_lowercase()).collect::<Vec<_>>();
+        stops.iter().position(|stop| *stop == zone).or_else(|| {
+            stops.iter()
+                .enumerate()
+                .filter(|(_, stop)| zone.starts_with(stop.as_str()))
+                .max_by_key(|(_, stop)| stop.len())
+                .map(|(i, _)| i)
+        })
+    }
+
+    /// The walk path as text, one zone per line, for editing.
+    pub fn walk_path_text(&self) -> String {
+        self.walk_path.join("\n")
+    }
+
+    pub fn set_walk_path_text(&mut self, text: &str) {
+        self.walk_path = text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect();
+    }
+
+    /// The zone rules as text, one `KEYWORD: zone` per line, for editing.
+    pub fn zone_rules_text(&self) -> String {
+        self.zone_rules.iter()
+            .map(|rule| format!("{}: {}", rule.keyword, rule.zone))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces the zone rules with the ones in `text`, ignoring lines that are not
+    /// `KEYWORD: zone`.
+    pub fn set_zone_rules_text(&mut self, text: &str) {
+        self.zone_rules = text.lines()
+            .filter_map(|line| {
+                let (keyword, zone) = line.split_at(line.rfind(':')?);
+                Some(ZoneRule { keyword: keyword.trim().to_owned(), zone: zone[1..].trim().to_owned() })
+            })
+            .filter(|rule| !rule.keyword.is_empty() && !rule.zone.is_empty())
+            .collect();
+    }
+}
+
+/// A product to pick, and how many units of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PickLine {
+    pub product_name: String,
+    pub quantity: u32,
+    /// Where the product is kept, from the catalogue; empty if unknown.
+    pub location: String,
+}
+
+/// The products to pick in one zone; the zone is empty for the products of no zone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PickGroup {
+    pub zone: String,
+    pub lines: Vec<PickLine>,
+}
+
+/// The units of each product in `rows`, with the components of bundles instead of the bundles if
+/// the catalogue says so, grouped by zone. The zones come in the order of the walk path, then
+/// the others by name, then the products of no zone; the products of a zone are sorted by
+/// location and name.
+pub fn pick_list(rows: &[WooCommerceRow], catalogue: &Catalogue) -> Vec<PickGroup> {
+    let products = catalogue.index(rows);
+    let mut quantities: BTreeMap<String, (u32, Option<&Product>)> = BTreeMap::new();
+    for row in rows {
+        let components = if catalogue.bundles.expand {
+            catalogue.bundles.expand(&row.product_name, row.quantity)
+        } else {
+            vec![BundleComponent { product_name: row.product_name.clone(), quantity: row.quantity }]
+        };
+        for component in components {
+            let product = if component.product_name == row.product_name {
+                products.product(row)
+            } else {
+                products.product_named(&component.product_name)
+            };
+            quantities.entry(component.product_name).or_insert((0, product)).0 += component.quantity;
+        }
+    }
+
+    let options = &catalogue.pick_list;
+    let mut groups: Vec<PickGroup> = Vec::new();
+    for (product_name, (quantity, product)) in quantities {
+        let zone = options.zone(product, &product_name);
+        let line = PickLine { product_name, quantity, location: product.map(|p| p.location.clone()).unwrap_or_default() };
+        match groups.iter_mut().find(|group| group.zone.to_lowercase() == zone.to_lowercase()) {
+            Some(group) => group.lines.push(line),
+            None => groups.push(PickGroup { zone, lines: vec![line] }),
+        }
+    }
+    for group in &mut groups {
+        group.lines.sort_by(|a, b| a.location.cmp(&b.location).then_with(|| a.product_name.cmp(&b.product_name)));
+    }
+    groups.sort_by_key(|group| (group.zone.is_empty(), options.stop(&group.zone).unwrap_or(usize::MAX), group.zone.to_lowercase()));
+    groups
+}
+
+#[cfg(test)]
+use crate::woocsv::sample_data;
+
+#[test]
+fn test_zone_rules_text() {
+    let mut options = PickListOptions::default();
+    options.set_zone_rules_text("YOGURT: Caseificio Rossi\nno zone\nPANE:");
+    assert_eq!(options.zone_rules, vec![ZoneRule { keyword: "YOGURT".to_owned(), zone: "Caseificio Rossi".to_owned() }]);
+    assert_eq!(options.zone_rules_text(), "YOGURT: Caseificio Rossi");
+    options.set_walk_path_text("  B\n\nA1\nA\n");
+    assert_eq!(options.walk_path, vec!["B", "A1", "A"]);
+    assert_eq!((options.stop("a1"), options.stop("A3"), options.stop("C")), (Some(1), Some(2), None));
+}
+
+#[test]
+fn test_pick_list() {
+    let rows = sample_data().data;
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text("Name;Producer;Location\n\
+                        ZUCCHINE 500 g;Orto Bianchi;B2\n\
+                        INSALATA VARIA 500 g;Orto Bianchi;B1\n\
+                        YOGURT DI CAPRA 500 g;Caseificio Rossi;A1").unwrap();
+    catalogue.bundles.set_text("SELEZIONE B \"IL VEGETARIANO\"\n    2 ZUCCHINE 500 g\n    1 YOGURT DI CAPRA 500 g");
+    catalogue.bundles.expand = true;
+    catalogue.pick_list.set_zone_rules_text("PANE: Forno Verdi");
+    catalogue.pick_list.set_walk_path_text("Forno Verdi\nCaseificio Rossi");
+    let zones = |groups: &[PickGroup]| groups.iter()
+        .map(|group| format!("{}: {}", group.zone, group.lines.iter().map(|l| format!("{} {}", l.quantity, l.product_name)).collect::<Vec<_>>().join(", ")))
+        .collect::<Vec<_>>();
+
+    let groups = pick_list(&rows, &catalogue);
+    assert_eq!(zones(&groups[..3]), vec![
+        "Forno Verdi: 1 PANE AI CEREALI ANTICHI 500 g",
+        "Caseificio Rossi: 3 YOGURT DI CAPRA 500 g",
+        "Orto Bianchi: 1 INSALATA VARIA 500 g, 4 ZUCCHINE 500 g",
+    ]);
+    assert_eq!(groups.last().unwrap().zone, "");
+
+    catalogue.pick_list.group_by = PickGroupBy::Location;
+    catalogue.pick_list.set_walk_path_text("B\nA");
+    let groups = pick_list(&rows, &catalogue);
+    assert_eq!(zones(&groups[..3]), vec!["B1: 1 INSALATA VARIA 500 g", "B2: 4 ZUCCHINE 500 g", "A1: 3 YOGURT DI CAPRA 500 g"]);
+}
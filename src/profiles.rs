@@ -0,0 +1,135 @@
+use crate::columns::Field;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const PROFILES_KEY: &str = "campagnamica.profiles";
+
+/// A named mapping from the header names of one particular export plugin to the fields of
+/// `WooCommerceRow`. Fields that are not mapped are looked up by their built-in aliases.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub headers: BTreeMap<Field, String>,
+}
+
+impl ColumnProfile {
+    pub fn new(name: &str) -> Self {
+        ColumnProfile { name: name.to_owned(), headers: BTreeMap::new() }
+    }
+
+    /// The header configured for `field`, if any.
+    pub fn header(&self, field: Field) -> Option<&str> {
+        self.headers.get(&field)
+            .map(|h| h.trim())
+            .filter(|h| !h.is_empty())
+    }
+
+    pub fn set_header(&mut self, field: Field, header: String) {
+        if header.trim().is_empty() {
+            self.headers.remove(&field);
+        } else {
+            self.headers.insert(field, header);
+        }
+    }
+}
+
+impl Default for ColumnProfile {
+    fn default() -> Self {
+        ColumnProfile::new("WooCommerce")
+    }
+}
+
+/// All the profiles known to the browser, and which one is in use.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Profiles {
+    pub profiles: Vec<ColumnProfile>,
+    pub selected: usize,
+}
+
+impl Profiles {
+    /// The profiles as loaded from the browser, made safe to use: the default ones if there are
+    /// none, and the last one selected if the selection is past the end.
+    pub fn checked(self) -> Profiles {
+        if self.profiles.is_empty() {
+            return Profiles::default();
+        }
+        let selected = self.selected.min(self.profiles.len() - 1);
+        Profiles { selected, ..self }
+    }
+
+    pub fn selected(&self) -> &ColumnProfile {
+        &self.profiles[self.selected]
+    }
+
+    pub fn selected_mut(&mut self) -> &mut ColumnProfile {
+        &mut self.profiles[self.selected]
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.profiles.len() {
+            self.selected = index;
+        }
+    }
+
+    /// Adds a profile named `name` and selects it. The new profile starts as a copy of the
+    /// selected one, since different exports usually differ in only a few columns.
+    pub fn add(&mut self, name: &str) {
+        let mut profile = self.selected().clone();
+        profile.name = name.to_owned();
+        self.profiles.push(profile);
+        self.selected = self.profiles.len() - 1;
+    }
+
+    /// Removes the selected profile; the last remaining one cannot be removed.
+    pub fn remove_selected(&mut self) {
+        if self.profiles.len() > 1 {
+            self.profiles.remove(self.selected);
+            self.selected = self.selected.saturating_sub(1);
+        }
+    }
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles { profiles: vec![ColumnProfile::default()], selected: 0 }
+    }
+}
+
+#[test]
+fn test_add_and_remove_profiles() {
+    let mut profiles = Profiles::default();
+    profiles.selected_mut().set_header(Field::OrderId, "Numero".to_owned());
+    profiles.add("Bergamo");
+    assert_eq!(profiles.selected, 1);
+    assert_eq!(profiles.selected().header(Field::OrderId), Some("Numero"));
+
+    profiles.selected_mut().set_header(Field::OrderId, " ".to_owned());
+    assert_eq!(profiles.selected().header(Field::OrderId), None);
+    assert_eq!(profiles.profiles[0].header(Field::OrderId), Some("Numero"));
+
+    profiles.remove_selected();
+    profiles.remove_selected();
+    assert_eq!(profiles.profiles.len(), 1);
+    assert_eq!(profiles.selected().name, "WooCommerce");
+}
+
+#[test]
+fn test_profiles_round_trip_through_json() {
+    let mut profiles = Profiles::default();
+    profiles.add("Milano");
+    profiles.selected_mut().set_header(Field::Quantity, "Pezzi".to_owned());
+    let json = serde_json::to_string(&profiles).unwrap();
+    let restored: Profiles = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, profiles);
+}
+
+#[test]
+fn test_checked_profiles() {
+    let empty: Profiles = serde_json::from_str(r#"{"profiles":[],"selected":0}"#).unwrap();
+    assert_eq!(empty.checked(), Profiles::default());
+    let mut profiles = Profiles::default();
+    profiles.add("Milano");
+    profiles.selected = 5;
+    let profiles = profiles.checked();
+    assert_eq!(profiles.selected().name, "Milano");
+}
@@ -0,0 +1,126 @@
+use crate::money::Money;
+use crate::woocsv::OrderDetails;
+use serde::{Deserialize, Serialize};
+
+pub const RECONCILE_KEY: &str = "campagnamica.reconcile";
+
+/// A VAT rate for the products whose name contains `keyword` (case-insensitive).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VatRule {
+    pub keyword: String,
+    pub percent: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileConfig {
+    /// VAT added to the item prices of products that match no rule. Zero by default, because
+    /// the item prices in our exports already add up to the order total.
+    pub default_vat: u32,
+    pub vat_rules: Vec<VatRule>,
+    /// Largest difference between declared and expected total that is not reported.
+    pub tolerance: Money,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        ReconcileConfig { default_vat: 0, vat_rules: Vec::new(), tolerance: Money::from_cents(5) }
+    }
+}
+
+impl ReconcileConfig {
+    pub fn vat(&self, product_name: &str) -> u32 {
+        let name = product_name.to_lowercase();
+        self.vat_rules.iter()
+            .find(|rule| name.contains(&rule.keyword.to_lowercase()))
+            .map_or(self.default_vat, |rule| rule.percent)
+    }
+
+    /// The VAT rules as text, one `KEYWORD: percent` per line, for editing.
+    pub fn vat_rules_text(&self) -> String {
+        self.vat_rules.iter()
+            .map(|rule| format!("{}: {}", rule.keyword, rule.percent))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces the VAT rules with the ones in `text`, ignoring lines that are not `KEYWORD: percent`.
+    pub fn set_vat_rules_text(&mut self, text: &str) {
+        self.vat_rules = text.lines()
+            .filter_map(|line| {
+                let (keyword, percent) = line.split_at(line.rfind(':')?);
+                Some(VatRule { keyword: keyword.trim().to_owned(), percent: percent[1..].trim().parse().ok()? })
+            })
+            .filter(|rule| !rule.keyword.is_empty())
+            .collect();
+    }
+}
+
+/// The total of an order as recomputed from its items, next to the one declared in the export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reconciliation {
+    pub order_id: u32,
+    pub customer_name: String,
+    pub declared: Money,
+    pub items: Money,
+    pub vat: Money,
+    pub shipping: Money,
+}
+
+impl Reconciliation {
+    pub fn expected(&self) -> Money {
+        self.items + self.vat + self.shipping
+    }
+
+    /// How much more the customer was charged than the items account for.
+    pub fn difference(&self) -> Money {
+        self.declared - self.expected()
+    }
+}
+
+/// Recomputes the total of each order (items × quantity, plus VAT, plus shipping) and returns
+/// the orders whose declared total differs by more than the configured tolerance.
+pub fn reconcile(orders: &[OrderDetails], config: &ReconcileConfig) -> Vec<Reconciliation> {
+    orders.iter()
+        .map(|order| {
+            let items = order.packages.iter().flatten();
+            Reconciliation {
+                order_id: order.order_id,
+                customer_name: order.customer_name.clone(),
+                declared: order.order_total,
+                items: items.clone().map(|item| item.item_price * item.quantity).sum(),
+                vat: items.map(|item| (item.item_price * item.quantity).percent(config.vat(&item.product_name))).sum(),
+                shipping: order.shipping,
+            }
+        })
+        .filter(|r| r.difference().abs() > config.tolerance)
+        .collect()
+}
+
+#[cfg(test)]
+use crate::woocsv::sample_orders;
+
+#[test]
+fn test_reconcile_flags_mismatching_totals() {
+    let orders = sample_orders();
+    let mismatches = reconcile(&orders, &ReconcileConfig::default());
+    assert_eq!(mismatches.iter().map(|r| r.order_id).collect::<Vec<_>>(), vec![5357, 11099]);
+    assert_eq!(mismatches[0].expected(), Money::from_cents(5290));
+    assert_eq!(mismatches[0].difference(), Money::euros(5));
+    assert_eq!(mismatches[1].items, Money::from_cents(340));
+}
+
+#[test]
+fn test_reconcile_with_vat() {
+    let orders = sample_orders();
+    let mut config = ReconcileConfig { default_vat: 10, ..ReconcileConfig::default() };
+    config.set_vat_rules_text("SELEZIONE: 0\ninsalata:4\nnot a rule");
+    assert_eq!(config.vat_rules.len(), 2);
+    assert_eq!(config.vat("Insalata varia 500 g"), 4);
+
+    let mismatches = reconcile(&orders, &config);
+    let first = &mismatches[0];
+    assert_eq!(first.order_id, 5358);
+    // 3,50 + 4,00 + 4,60 at 10%, the selection at 0%
+    assert_eq!(first.vat, Money::from_cents(121));
+    assert_eq!(first.difference(), Money::from_cents(-121));
+}
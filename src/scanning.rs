@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use crate::barcode::PackageCode;
+use crate::woocsv::OrderDetails;
+
+/// What a scanned code did.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scan {
+    /// Ticked off a package.
+    Ticked(PackageCode),
+    /// A package that was already ticked off.
+    Duplicate(PackageCode),
+    /// Text that is not the code of a package of the orders: a misread, or the label of another
+    /// day or of an order packed since in a different number of packages.
+    Unknown(String),
+}
+
+impl Scan {
+    /// Whether the scan needs a look, being a duplicate or unknown.
+    pub fn is_warning(&self) -> bool {
+        !matches!(self, Scan::Ticked(_))
+    }
+}
+
+impl Display for Scan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scan::Ticked(code) => write!(f, "{}: package {} of {} of order {}", code, code.package, code.packages, code.order_id),
+            Scan::Duplicate(code) => write!(f, "{}: package {} of {} of order {} was already scanned", code, code.package, code.packages, code.order_id),
+            Scan::Unknown(text) => write!(f, "{:?} is not a package of these orders", text),
+        }
+    }
+}
+
+/// An order while its packages are scanned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderProgress {
+    pub order_id: u32,
+    pub customer_name: String,
+    pub packages: usize,
+    /// The numbers of the packages not scanned yet, from 1.
+    pub missing: Vec<usize>,
+}
+
+impl OrderProgress {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// The packages scanned at loading, ticked off against the orders, and all the scans, latest
+/// last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Verification {
+    scanned: BTreeSet<PackageCode>,
+    pub scans: Vec<Scan>,
+}
+
+impl Verification {
+    /// Ticks off the package of the code in `text`, if it is one of `orders`, and tells what the
+    /// scan did. The code must have the number of packages of the order, or it is from older
+    /// labels.
+    pub fn scan(&mut self, orders: &[OrderDetails], text: &str) -> &Scan {
+        let scan = match PackageCode::decode(text) {
+            Some(code) if orders.iter().any(|order| order.order_id == code.order_id && order.packages.len() == code.packages) => {
+                if self.scanned.insert(code) { Scan::Ticked(code) } else { Scan::Duplicate(code) }
+            }
+            _ => Scan::Unknown(text.trim().to_owned()),
+        };
+        self.scans.push(scan);
+        self.scans.last().expect("a scan just made")
+    }
+
+    /// The progress of each of `orders`, in the same order.
+    pub fn progress(&self, orders: &[OrderDetails]) -> Vec<OrderProgress> {
+        orders.iter()
+            .map(|order| OrderProgress {
+                order_id: order.order_id,
+                customer_name: order.customer_name.clone(),
+                packages: order.packages.len(),
+                missing: (0..order.packages.len())
+                    .filter(|&i| !self.scanned.contains(&order.package_code(i)))
+                    .map(|i| i + 1)
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+use crate::woocsv::sample_orders;
+
+#[test]
+fn test_verification() {
+    let orders = sample_orders();
+    assert_eq!(orders.iter().map(|order| (order.order_id, order.packages.len())).collect::<Vec<_>>(), vec![(5358, 2), (5357, 2), (11099, 1)]);
+    let mut verification = Verification::default();
+    let code = PackageCode { order_id: 5358, package: 2, packages: 2 };
+    assert_eq!(verification.scan(&orders, "5358-2/2\n"), &Scan::Ticked(code));
+    assert_eq!(verification.scan(&orders, "5358-2/2"), &Scan::Duplicate(code));
+    assert_eq!(verification.scan(&orders, "5358-2/3"), &Scan::Unknown("5358-2/3".to_owned()));
+    assert_eq!(verification.scan(&orders, "1234-1/1"), &Scan::Unknown("1234-1/1".to_owned()));
+    assert_eq!(verification.scan(&orders, "11099-1/1"), &Scan::Ticked(PackageCode { order_id: 11099, package: 1, packages: 1 }));
+    assert_eq!(verification.scans.iter().filter(|scan| scan.is_warning()).count(), 3);
+
+    let progress = verification.progress(&orders);
+    assert_eq!(progress.iter().map(|order| order.missing.clone()).collect::<Vec<_>>(), vec![vec![1], vec![1, 2], vec![]]);
+    assert_eq!(progress.iter().map(OrderProgress::is_complete).collect::<Vec<_>>(), vec![false, false, true]);
+    assert_eq!(Scan::Duplicate(code).to_string(), "5358-2/2: package 2 of 2 of order 5358 was already scanned");
+}
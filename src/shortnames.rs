@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+const UNITS: [&str; 12] = ["g", "gr", "grammi", "hg", "kg", "l", "lt", "litri", "cl", "ml", "pz", "pezzi"];
+
+/// A name in exports, and the name to print on labels instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NameMapping {
+    pub name: String,
+    pub short_name: String,
+}
+
+/// How the names of products are shortened on labels, so that they fit. The summary always
+/// shows the names as they are in the export.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortNames {
+    /// Names in exports, compared ignoring case, and their short names; these win over
+    /// everything else.
+    pub mappings: Vec<NameMapping>,
+    /// Whether quantities like `500 g` or `1,5 l` are left out.
+    pub drop_units: bool,
+    /// Words or phrases left out, compared ignoring case, e.g. `ALLE ERBE DI MONTAGNA`.
+    pub drop_phrases: Vec<String>,
+    /// Longer names are cut after a word and end with `…`; zero for no limit.
+    pub max_length: usize,
+}
+
+impl ShortNames {
+    /// The name to print for `name`: its mapping, or else `short_name` from the catalogue if
+    /// any, or else `name` shortened by the rules.
+    pub fn shorten(&self, name: &str, short_name: Option<&str>) -> String {
+        let lowercase = name.trim().to_lowercase();
+        if let Some(mapping) = self.mappings.iter().find(|m| m.name.trim().to_lowercase() == lowercase) {
+            return mapping.short_name.clone();
+        }
+        if let Some(short_name) = short_name.filter(|s| !s.trim().is_empty()) {
+            return short_name.to_owned();
+        }
+
+        let mut short = name.to_owned();
+        for phrase in self.drop_phrases.iter().filter(|p| !p.trim().is_empty()) {
+            short = remove_ignoring_case(&short, phrase.trim());
+        }
+        let mut words = short.split_whitespace().collect::<Vec<_>>();
+        if self.drop_units {
+            words = drop_units(&words);
+        }
+        let short = truncate(&words, self.max_length);
+        if short.is_empty() { name.to_owned() } else { short }
+    }
+
+    /// The mappings as text, one `NAME => SHORT NAME` per line, for editing.
+    pub fn mappings_text(&self) -> String {
+        self.mappings.iter()
+            .map(|m| format!("{} => {}", m.name, m.short_name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces the mappings with the ones in `text`, ignoring lines that are not
+    /// `NAME => SHORT NAME`.
+    pub fn set_mappings_text(&mut self, text: &str) {
+        self.mappings = text.lines()
+            .filter_map(|line| {
+                let (name, short_name) = line.split_at(line.find("=>")?);
+                Some(NameMapping { name: name.trim().to_owned(), short_name: short_name[2..].trim().to_owned() })
+            })
+            .filter(|m| !m.name.is_empty() && !m.short_name.is_empty())
+            .collect();
+    }
+
+    /// The phrases to drop, one per line, for editing.
+    pub fn drop_phrases_text(&self) -> String {
+        self.drop_phrases.join("\n")
+    }
+
+    pub fn set_drop_phrases_text(&mut self, text: &str) {
+        self.drop_phrases = text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect();
+    }
+}
+
+/// `text` without the occurrences of `phrase` that are whole words, ignoring case.
+fn remove_ignoring_case(text: &str, phrase: &str) -> String {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let phrase = phrase.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let matches = i + phrase.len() <= words.len()
+            && words[i..i + phrase.len()].iter().zip(&phrase).all(|(w, p)| w.to_lowercase() == *p);
+        if matches {
+            i += phrase.len();
+        } else {
+            kept.push(words[i]);
+            i += 1;
+        }
+    }
+    kept.join(" ")
+}
+
+fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+        && word.chars().any(|c| c.is_ascii_digit())
+}
+
+fn is_unit(word: &str) -> bool {
+    UNITS.contains(&word.trim_end_matches('.').to_lowercase().as_str())
+}
+
+/// `words` without quantities, either as a number followed by a unit (`500 g`) or joined
+/// (`500g`).
+fn drop_units<'a>(words: &[&'a str]) -> Vec<&'a str> {
+    let mut kept = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        let digits = word.find(|c: char| !(c.is_ascii_digit() || c == ',' || c == '.')).unwrap_or(word.len());
+        if is_number(word) && words.get(i + 1).is_some_and(|next| is_unit(next)) {
+            i += 2;
+        } else if digits > 0 && is_number(&word[..digits]) && is_unit(&word[digits..]) {
+            i += 1;
+        } else {
+            kept.push(word);
+            i += 1;
+        }
+    }
+    kept
+}
+
+/// The words joined, cut after the last word that fits in `max_length` characters with `…`.
+fn truncate(words: &[&str], max_length: usize) -> String {
+    let joined = words.join(" ");
+    if max_length == 0 || joined.chars().count() <= max_length {
+        return joined;
+    }
+    let mut short = String::new();
+    for word in words {
+        let length = short.chars().count() + usize::from(!short.is_empty()) + word.chars().count();
+        if length + 1 > max_length {
+            break;
+        }
+        if !short.is_empty() {
+            short.push(' ');
+        }
+        short.push_str(word);
+    }
+    if short.is_empty() {
+        short = joined.chars().take(max_length.saturating_sub(1)).collect();
+    }
+    short.push('…');
+    short
+}
+
+#[test]
+fn test_shorten() {
+    let mut names = ShortNames { drop_units: true, ..ShortNames::default() };
+    names.set_mappings_text("SELEZIONE B \"IL VEGETARIANO\" => Selezione vegetariana\nnot a mapping");
+    names.set_drop_phrases_text("alle erbe di montagna\n\n");
+    assert_eq!(names.mappings.len(), 1);
+
+    assert_eq!(names.shorten("selezione b \"il vegetariano\"", None), "Selezione vegetariana");
+    assert_eq!(names.shorten("GALLETTO VALLE SPLUGA ALLE ERBE DI MONTAGNA 500 g", None), "GALLETTO VALLE SPLUGA");
+    assert_eq!(names.shorten("GALLETTO VALLE SPLUGA 500 g", Some("Galletto")), "Galletto");
+    assert_eq!(names.shorten("OLIO EVO 0,75l", None), "OLIO EVO");
+    assert_eq!(names.shorten("500 g", None), "500 g");
+
+    names.max_length = 12;
+    assert_eq!(names.shorten("CARNE TRITA DI MANZO 500 g", None), "CARNE TRITA…");
+    assert_eq!(names.shorten("PRECIPITEVOLISSIMEVOLMENTE", None), "PRECIPITEVO…");
+    assert_eq!(names.shorten("PANE 1 kg", None), "PANE");
+}
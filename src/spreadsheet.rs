@@ -0,0 +1,125 @@
+use crate::profiles::ColumnProfile;
+use crate::woocsv::{parse_records, InputData};
+use anyhow::{anyhow, Context, Result};
+use calamine::{open_workbook_auto_from_rs, DataType, Reader};
+use csv::{Position, StringRecord};
+use std::io::Cursor;
+
+/// Whether `data` looks like an Excel or LibreOffice workbook rather than text. XLSX and ODS
+/// files are zip archives, XLS files are OLE compound documents.
+pub fn is_spreadsheet(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04") || data.starts_with(b"\xd0\xcf\x11\xe0")
+}
+
+pub fn sheet_names(data: &[u8]) -> Result<Vec<String>> {
+    let workbook = open_workbook_auto_from_rs(Cursor::new(data)).context("Cannot open the spreadsheet")?;
+    Ok(workbook.sheet_names().to_vec())
+}
+
+/// Reads the orders from the sheet named `sheet`, or from the first sheet of the workbook.
+/// The first row of the sheet must hold the headers, as in the CSV export.
+pub fn parse_spreadsheet(data: &[u8], sheet: Option<&str>, profile: &ColumnProfile) -> Result<InputData> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(data)).context("Cannot open the spreadsheet")?;
+    let name = match sheet {
+        Some(name) => name.to_owned(),
+        None => workbook.sheet_names().first().cloned().ok_or_else(|| anyhow!("The spreadsheet has no sheets"))?,
+    };
+    let range = workbook.worksheet_range(&name)
+        .ok_or_else(|| anyhow!("No sheet named {}", name))?
+        .with_context(|| format!("Cannot read sheet {}", name))?;
+
+    let mut rows = range.rows()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut record = row.iter().map(cell_to_string).collect::<StringRecord>();
+            let mut position = Position::new();
+            position.set_line(range.start().map_or(0, |(row, _)| row as u64) + i as u64 + 1);
+            record.set_position(Some(position));
+            record
+        })
+        .filter(|record| record.iter().any(|cell| !cell.is_empty()));
+    let header = rows.next().unwrap_or_default();
+    let (data, diagnostics) = parse_records(&header, rows.map(Ok), profile)?;
+    Ok(InputData { data, dialect: None, diagnostics })
+}
+
+/// Formats a cell the way it would appear in the CSV export, so that the same field parsing
+/// applies: whole numbers without decimals (order IDs, postcodes, phone numbers), and dates as
+/// `YYYY/MM/DD`.
+fn cell_to_string(cell: &DataType) -> String {
+    match cell {
+        DataType::Empty => String::new(),
+        DataType::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+        DataType::DateTime(serial) => excel_date(*serial),
+        other => other.to_string(),
+    }
+}
+
+/// Converts an Excel serial date (days since 1899-12-30) to `YYYY/MM/DD`, keeping the time of
+/// day if there is one.
+fn excel_date(serial: f64) -> String {
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = serial.floor() as i64 - 25569 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let minutes = (serial.fract() * 24.0 * 60.0).round() as i64;
+    if minutes == 0 {
+        format!("{}/{:02}/{:02}", year, month, day)
+    } else {
+        format!("{}/{:02}/{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+use crate::money::Money;
+
+#[cfg(test)]
+const XLSX: &[u8] = include_bytes!("data.xlsx");
+
+#[cfg(test)]
+const ODS: &[u8] = include_bytes!("data.ods");
+
+#[test]
+fn test_parse_xlsx() {
+    assert!(is_spreadsheet(XLSX));
+    assert_eq!(sheet_names(XLSX).unwrap(), vec!["Ordini", "Milano"]);
+
+    let parsed = parse_spreadsheet(XLSX, None, &ColumnProfile::default()).unwrap();
+    assert_eq!(parsed.data.len(), 10);
+    assert_eq!(parsed.data[0].order_id, 5358);
+    assert_eq!(parsed.data[0].shipping_postcode, "20146");
+    assert_eq!(parsed.data[0].billing_phone_number, "3355700000");
+    assert_eq!(parsed.data[1].product_name, "CARNE TRITA DI MANZO PER RAGU' E POLPETTE 500 g");
+
+    let milano = parse_spreadsheet(XLSX, Some("Milano"), &ColumnProfile::default()).unwrap();
+    assert_eq!(milano.data.len(), 4);
+    assert!(parse_spreadsheet(XLSX, Some("Bergamo"), &ColumnProfile::default()).is_err());
+}
+
+#[test]
+fn test_parse_ods() {
+    assert!(is_spreadsheet(ODS));
+    let parsed = parse_spreadsheet(ODS, None, &ColumnProfile::default()).unwrap();
+    assert_eq!(parsed.data.len(), 10);
+    assert_eq!(parsed.data[9].order_id, 11099);
+    assert_eq!(parsed.data[9].quantity, 2);
+    assert_eq!(parsed.data[9].order_total, Money::from_cents(8020));
+}
+
+#[test]
+fn test_excel_date() {
+    assert_eq!(excel_date(43975.0), "2020/05/24");
+    assert_eq!(excel_date(44509.4020833333), "2021/11/09 09:39");
+}
+
+#[test]
+fn test_csv_is_not_a_spreadsheet() {
+    assert!(!is_spreadsheet(include_bytes!("data.csv")));
+}
@@ -0,0 +1,123 @@
+use crate::dialect::excel_csv;
+use crate::money::Money;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// The totals of one product across all the orders.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SummaryRow {
+    pub product_name: String,
+    pub total_quantity: u32,
+    /// The number of orders with the product.
+    pub orders: u32,
+    /// Quantity × item price, over all the orders.
+    pub revenue: Money,
+    /// Units by shipping method.
+    pub by_delivery: BTreeMap<String, u32>,
+}
+
+/// A column the summary can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryColumn {
+    Product,
+    Quantity,
+    Orders,
+    Revenue,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SummarySort {
+    pub column: SummaryColumn,
+    pub descending: bool,
+}
+
+impl Default for SummarySort {
+    fn default() -> Self {
+        SummarySort { column: SummaryColumn::Product, descending: false }
+    }
+}
+
+impl SummarySort {
+    /// The sort after clicking on the header of `column`: the other way round if the summary is
+    /// already sorted by it, or else by it, biggest first for numbers.
+    pub fn toggle(self, column: SummaryColumn) -> SummarySort {
+        if column == self.column {
+            SummarySort { column, descending: !self.descending }
+        } else {
+            SummarySort { column, descending: column != SummaryColumn::Product }
+        }
+    }
+
+    /// Sorts `rows`, by product name when the column is the same.
+    pub fn sort(self, rows: &mut [SummaryRow]) {
+        rows.sort_by(|a, b| {
+            let ordering = match self.column {
+                SummaryColumn::Product => Ordering::Equal,
+                SummaryColumn::Quantity => a.total_quantity.cmp(&b.total_quantity),
+                SummaryColumn::Orders => a.orders.cmp(&b.orders),
+                SummaryColumn::Revenue => a.revenue.cmp(&b.revenue),
+            }.then_with(|| a.product_name.cmp(&b.product_name));
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+    }
+
+    /// A marker for the header of `column`, showing whether and how the summary is sorted by it.
+    pub fn arrow(self, column: SummaryColumn) -> &'static str {
+        match (column == self.column, self.descending) {
+            (false, _) => "",
+            (true, false) => " ▲",
+            (true, true) => " ▼",
+        }
+    }
+}
+
+/// The shipping methods found in `rows`, sorted, for the columns of the breakdown.
+pub fn delivery_methods(rows: &[SummaryRow]) -> Vec<String> {
+    let mut methods = rows.iter().flat_map(|row| row.by_delivery.keys().cloned()).collect::<Vec<_>>();
+    methods.sort();
+    methods.dedup();
+    methods
+}
+
+/// The summary as CSV for Excel, see `excel_csv`, with amounts with a decimal comma.
+pub fn to_csv(rows: &[SummaryRow]) -> String {
+    let methods = delivery_methods(rows);
+    let header = ["Prodotto", "Quantità", "Ordini", "Incasso"].iter().map(|h| h.to_string()).chain(methods.iter().cloned());
+    excel_csv(header, rows.iter().map(|row| {
+        vec![row.product_name.clone(), row.total_quantity.to_string(), row.orders.to_string(), row.revenue.to_string()]
+            .into_iter()
+            .chain(methods.iter().map(move |m| row.by_delivery.get(m).copied().unwrap_or(0).to_string()))
+    }))
+}
+
+#[cfg(test)]
+fn row(product_name: &str, total_quantity: u32, orders: u32, euros: i64) -> SummaryRow {
+    SummaryRow { product_name: product_name.to_owned(), total_quantity, orders, revenue: Money::euros(euros), ..SummaryRow::default() }
+}
+
+#[test]
+fn test_sort() {
+    let mut rows = vec![row("PANE", 3, 2, 6), row("LATTE", 5, 1, 5), row("UOVA", 3, 3, 9)];
+    let sort = SummarySort::default().toggle(SummaryColumn::Quantity);
+    assert_eq!(sort, SummarySort { column: SummaryColumn::Quantity, descending: true });
+    sort.sort(&mut rows);
+    assert_eq!(rows.iter().map(|r| r.product_name.as_str()).collect::<Vec<_>>(), vec!["LATTE", "UOVA", "PANE"]);
+    sort.toggle(SummaryColumn::Quantity).sort(&mut rows);
+    assert_eq!(rows.iter().map(|r| r.product_name.as_str()).collect::<Vec<_>>(), vec!["PANE", "UOVA", "LATTE"]);
+    SummarySort::default().sort(&mut rows);
+    assert_eq!(rows[0].product_name, "LATTE");
+}
+
+#[test]
+fn test_to_csv() {
+    let mut pane = row("PANE", 3, 2, 6);
+    pane.by_delivery.insert("standard".to_owned(), 2);
+    pane.by_delivery.insert("Ritiro".to_owned(), 1);
+    let mut latte = row("LATTE; INTERO", 5, 1, 5);
+    latte.by_delivery.insert("standard".to_owned(), 5);
+    assert_eq!(to_csv(&[pane, latte]), "\u{feff}\
+Prodotto;Quantità;Ordini;Incasso;Ritiro;standard
+PANE;3;2;6,00;1;2
+\"LATTE; INTERO\";5;1;5,00;0;5
+");
+}
@@ -0,0 +1,235 @@
+use crate::catalogue::Temperature;
+use crate::pdf;
+use crate::woocsv::OrderDetails;
+
+/// Labels of 4 × 6 inches at 203 dpi, the size of the courier's rolls, in dots.
+const WIDTH: u32 = 812;
+const HEIGHT: u32 = 1218;
+const MARGIN: u32 = 30;
+/// Where the address starts.
+const ADDRESS_X: u32 = 430;
+const QUANTITY_WIDTH: u32 = 130;
+const BARCODE_HEIGHT: u32 = 100;
+/// The side of the QR code, 21 modules of 6 dots with the margin ZPL leaves around it.
+const QR_SIZE: u32 = 140;
+
+/// The sizes of text, as the fonts of EPL, which are fixed-width; the scalable font of ZPL is
+/// used at about the same size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Size {
+    Normal,
+    Large,
+}
+
+impl Size {
+    /// Dots from one character to the next.
+    fn advance(self) -> u32 {
+        match self {
+            Size::Normal => 14,
+            Size::Large => 16,
+        }
+    }
+
+    fn height(self) -> u32 {
+        match self {
+            Size::Normal => 20,
+            Size::Large => 24,
+        }
+    }
+
+    /// How many characters fit in `width` dots.
+    fn fit(self, width: u32) -> usize {
+        (width / self.advance()) as usize
+    }
+}
+
+/// What a thermal label is made of, in dots from the top left corner.
+#[derive(Clone, Debug, PartialEq)]
+enum Element {
+    /// White on black if `reverse`.
+    Text { x: u32, y: u32, size: Size, reverse: bool, text: String },
+    /// A rectangle with lines `thickness` dots thick, filled if as thick as it is tall.
+    Box { x: u32, y: u32, width: u32, height: u32, thickness: u32 },
+    /// The code of the package as Code 128, with the code printed below.
+    Barcode { x: u32, y: u32, data: String },
+    /// The code of the package as a QR code, which only ZPL prints: EPL has no QR codes.
+    Qr { x: u32, y: u32, data: String },
+}
+
+/// `text` cut to `max` characters, ending with `...` if cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_owned()
+    } else {
+        format!("{}...", text.chars().take(max.saturating_sub(3)).collect::<String>().trim_end())
+    }
+}
+
+/// The label of the `package`-th package of `order`: temperature, order details, address,
+/// `Collo i di n`, the items that fit and the barcodes of the package.
+fn layout(order: &OrderDetails, package: usize) -> Vec<Element> {
+    let width = WIDTH - 2 * MARGIN;
+    let mut elements = Vec::new();
+    let text = |x: u32, y: u32, size: Size, reverse: bool, text: &str, room: u32| Element::Text {
+        x, y, size, reverse, text: truncate(text, size.fit(room)),
+    };
+
+    if let Some(temperature) = order.package_temperature(package) {
+        let banner = temperature.banner();
+        match temperature {
+            Temperature::Frozen => elements.push(Element::Box { x: MARGIN, y: MARGIN, width, height: 60, thickness: 60 }),
+            Temperature::Chilled => {
+                elements.push(Element::Box { x: MARGIN, y: MARGIN, width, height: 60, thickness: 3 });
+                elements.push(Element::Box { x: MARGIN + 8, y: MARGIN + 8, width: width - 16, height: 44, thickness: 3 });
+            }
+            Temperature::Ambient => elements.push(Element::Box { x: MARGIN, y: MARGIN, width, height: 60, thickness: 4 }),
+        }
+        let banner_x = MARGIN + width.saturating_sub(banner.chars().count() as u32 * Size::Large.advance()) / 2;
+        elements.push(text(banner_x, MARGIN + 18, Size::Large, temperature == Temperature::Frozen, banner, width));
+    }
+
+    let mut y = 120;
+    let details = [
+        format!("Ordine N.: {}", order.order_id),
+        format!("Data: {}", order.order_date),
+        format!("Tel.: {}", order.billing_phone_number),
+    ];
+    for (i, line) in details.iter().enumerate() {
+        elements.push(text(MARGIN, y + 34 * i as u32, Size::Normal, false, line, ADDRESS_X - MARGIN - 10));
+    }
+    let address = ["Indirizzo:", &order.customer_name, &order.shipping_address_line_1, &order.shipping_address_line_2, &format!("Milano, {}", order.shipping_postcode), "Italia"];
+    for line in address.iter().filter(|line| !line.trim().is_empty()) {
+        elements.push(text(ADDRESS_X, y, Size::Normal, false, line, WIDTH - MARGIN - ADDRESS_X));
+        y += 34;
+    }
+    y += 16;
+    elements.push(text(ADDRESS_X, y, Size::Large, false, &format!("Collo {} di {}", package + 1, order.packages.len()), WIDTH - MARGIN - ADDRESS_X));
+    y += 60;
+
+    elements.push(Element::Box { x: MARGIN, y, width, height: 40, thickness: 40 });
+    elements.push(text(MARGIN + 10, y + 10, Size::Normal, true, "Quantità", QUANTITY_WIDTH));
+    elements.push(text(MARGIN + QUANTITY_WIDTH, y + 10, Size::Normal, true, "Prodotto", width - QUANTITY_WIDTH));
+    y += 50;
+
+    let items = &order.packages[package];
+    let barcode_y = HEIGHT - MARGIN - BARCODE_HEIGHT - 40;
+    let rows = ((barcode_y - 20 - y) / 34) as usize;
+    let shown = if items.len() > rows { rows.saturating_sub(1) } else { items.len() };
+    for item in &items[..shown] {
+        let quantity = if item.ordered > item.quantity { format!("{} di {}", item.quantity, item.ordered) } else { item.quantity.to_string() };
+        elements.push(text(MARGIN + 10, y, Size::Normal, false, &quantity, QUANTITY_WIDTH - 10));
+        elements.push(text(MARGIN + QUANTITY_WIDTH, y, Size::Normal, false, &item.label_name, width - QUANTITY_WIDTH));
+        y += 34;
+    }
+    if shown < items.len() {
+        elements.push(text(MARGIN + QUANTITY_WIDTH, y, Size::Normal, false, &format!("... e altri {} prodotti", items.len() - shown), width - QUANTITY_WIDTH));
+    }
+
+    let code = order.package_code(package).to_string();
+    elements.push(Element::Barcode { x: MARGIN, y: barcode_y, data: code.clone() });
+    elements.push(Element::Qr { x: WIDTH - MARGIN - QR_SIZE, y: barcode_y, data: code });
+    elements
+}
+
+/// Field data for ZPL, with `^`, `~` and `\` as hexadecimal escapes (see `^FH`).
+fn zpl_escape(text: &str) -> String {
+    text.replace('\\', "\\5C").replace('^', "\\5E").replace('~', "\\7E")
+}
+
+/// The labels of all the packages of `orders` as a ZPL program, to send as it is to a Zebra
+/// printer; text is UTF-8.
+pub fn zpl(orders: &[OrderDetails]) -> String {
+    let mut program = String::new();
+    for order in orders {
+        for package in 0..order.packages.len() {
+            program.push_str(&format!("^XA\n^CI28\n^PW{}\n^LL{}\n", WIDTH, HEIGHT));
+            for element in layout(order, package) {
+                let line = match element {
+                    Element::Text { x, y, size, reverse, text } => format!(
+                        "^FO{},{}^A0N,{},{}{}^FH\\^FD{}^FS",
+                        x, y, size.height() + 8, size.advance() + 4, if reverse { "^FR" } else { "" }, zpl_escape(&text),
+                    ),
+                    Element::Box { x, y, width, height, thickness } => format!("^FO{},{}^GB{},{},{}^FS", x, y, width, height, thickness),
+                    Element::Barcode { x, y, data } => format!("^FO{},{}^BY3^BCN,{},Y,N,N^FD{}^FS", x, y, BARCODE_HEIGHT, data),
+                    Element::Qr { x, y, data } => format!("^FO{},{}^BQN,2,6^FDMA,{}^FS", x, y, data),
+                };
+                program.push_str(&line);
+                program.push('\n');
+            }
+            program.push_str("^XZ\n");
+        }
+    }
+    program
+}
+
+/// Text for EPL, between double quotes.
+fn epl_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The labels of all the packages of `orders` as an EPL program, for the older Zebra printers;
+/// text is in the Windows-1252 code page, as `I8,A` tells the printer.
+pub fn epl(orders: &[OrderDetails]) -> Vec<u8> {
+    let mut program = Vec::new();
+    for order in orders {
+        for package in 0..order.packages.len() {
+            program.extend_from_slice(format!("\nN\nI8,A,039\nq{}\nQ{},24\n", WIDTH, HEIGHT).as_bytes());
+            for element in layout(order, package) {
+                let line = match element {
+                    Element::Qr { .. } => continue,
+                    Element::Text { x, y, size, reverse, text } => format!(
+                        "A{},{},0,{},1,1,{},\"{}\"",
+                        x, y, if size == Size::Large { 4 } else { 3 }, if reverse { "R" } else { "N" }, epl_escape(&text),
+                    ),
+                    Element::Box { x, y, width, height, thickness } if thickness >= height => format!("LO{},{},{},{}", x, y, width, height),
+                    Element::Box { x, y, width, height, thickness } => format!("X{},{},{},{},{}", x, y, thickness, x + width, y + height),
+                    Element::Barcode { x, y, data } => format!("B{},{},0,1,3,6,{},B,\"{}\"", x, y, BARCODE_HEIGHT, data),
+                };
+                program.extend_from_slice(&pdf::encode(&line));
+                program.push(b'\n');
+            }
+            program.extend_from_slice(b"P1\n");
+        }
+    }
+    program
+}
+
+#[cfg(test)]
+use crate::catalogue::Catalogue;
+#[cfg(test)]
+use crate::packing::PackingOptions;
+#[cfg(test)]
+use crate::woocsv::sample_data;
+
+#[cfg(test)]
+fn orders() -> Vec<OrderDetails> {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_temperature_rules_text("GALLETTO: frozen\nYOGURT: chilled\nPANE: ambient");
+    sample_data().labels(&PackingOptions::default(), &catalogue)
+}
+
+#[test]
+fn test_zpl() {
+    assert_eq!(zpl(&orders()), include_str!("golden/labels.zpl"));
+}
+
+#[test]
+fn test_epl() {
+    assert_eq!(epl(&orders()), include_bytes!("golden/labels.epl").to_vec());
+}
+
+#[test]
+fn test_long_package() {
+    let mut orders = orders();
+    let item = orders[0].packages[0][0].clone();
+    orders[0].packages[0] = (1..=40).map(|i| crate::woocsv::OrderItem { label_name: format!("PRODOTTO {}", i), ..item.clone() }).collect();
+    let elements = layout(&orders[0], 0);
+    let last = elements.iter().rev().find_map(|element| match element {
+        Element::Text { text, y, .. } => Some((text.clone(), *y)),
+        _ => None,
+    }).unwrap();
+    assert!(last.0.starts_with("... e altri "));
+    assert!(last.1 < HEIGHT - MARGIN - BARCODE_HEIGHT - 40);
+    assert_eq!(truncate("CARNE TRITA DI MANZO", 10), "CARNE T...");
+    assert_eq!(zpl_escape("A^B~C\\"), "A\\5EB\\7EC\\5C");
+}
@@ -0,0 +1,196 @@
+//! Orders as returned by the WooCommerce REST API (`/wp-json/wc/v3/orders`), for when a script
+//! saves the API response instead of going through the CSV export plugin.
+
+use crate::money::Money;
+use crate::woocsv::{InputData, WooCommerceRow};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Order {
+    id: u32,
+    #[serde(default)]
+    date_created: String,
+    total: Money,
+    #[serde(default)]
+    shipping_total: Money,
+    #[serde(default)]
+    payment_method_title: String,
+    #[serde(default)]
+    transaction_id: String,
+    #[serde(default)]
+    billing: Address,
+    #[serde(default)]
+    shipping: Address,
+    #[serde(default)]
+    line_items: Vec<LineItem>,
+    #[serde(default)]
+    shipping_lines: Vec<ShippingLine>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Address {
+    first_name: String,
+    last_name: String,
+    address_1: String,
+    address_2: String,
+    postcode: String,
+    phone: String,
+}
+
+impl Address {
+    fn full_name(&self) -> String {
+        format!("{} {}", self.first_name, self.last_name).trim().to_owned()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LineItem {
+    name: String,
+    quantity: u32,
+    /// Unit price excluding tax.
+    price: Money,
+    #[serde(default)]
+    sku: String,
+    /// Options chosen by the customer, attributes of variations and notes of plugins.
+    #[serde(default)]
+    meta_data: Vec<MetaData>,
+}
+
+impl LineItem {
+    /// The name followed by the metadata the customer sees, e.g. `TORTA (Scritta: Auguri)`;
+    /// values already in the name, as the attributes of variations often are, are left out.
+    fn full_name(&self) -> String {
+        let meta = self.meta_data.iter()
+            .filter_map(MetaData::visible)
+            .filter(|(_, value)| !self.name.to_lowercase().contains(&value.to_lowercase()))
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect::<Vec<_>>();
+        if meta.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, meta.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaData {
+    key: String,
+    #[serde(default)]
+    value: serde_json::Value,
+    /// The key and value as shown to the customer, e.g. `Formato` and `500 g` for the key
+    /// `pa_formato` and the value `500-g`.
+    #[serde(default)]
+    display_key: Option<String>,
+    #[serde(default)]
+    display_value: Option<serde_json::Value>,
+}
+
+impl MetaData {
+    /// The key and the value as shown to the customer, or `None` for the entries WooCommerce
+    /// hides (keys starting with `_`) and for values that are not text or numbers.
+    fn visible(&self) -> Option<(String, String)> {
+        if self.key.starts_with('_') {
+            return None;
+        }
+        let value = match self.display_value.as_ref().unwrap_or(&self.value) {
+            serde_json::Value::String(text) => text.trim().to_owned(),
+            serde_json::Value::Number(number) => number.to_string(),
+            _ => return None,
+        };
+        let key = self.display_key.clone().unwrap_or_else(|| self.key.clone());
+        Some((key, value)).filter(|(_, value)| !value.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ShippingLine {
+    method_title: String,
+}
+
+/// Whether `data` looks like JSON rather than CSV.
+pub fn is_json(data: &[u8]) -> bool {
+    data.iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'[' || b == b'{')
+}
+
+/// Maps an array of orders to one row per line item, like the CSV export does.
+pub fn parse_orders_json(data: &[u8]) -> Result<InputData> {
+    let orders: Vec<Order> = serde_json::from_slice(data).context("Invalid WooCommerce orders JSON")?;
+    let data = orders.into_iter()
+        .flat_map(|order| {
+            let customer = if order.shipping.full_name().is_empty() { &order.billing } else { &order.shipping };
+            let row = WooCommerceRow {
+                order_id: order.id,
+                order_date: order.date_created.get(0..10).unwrap_or("").replace('-', "/"),
+                customer_name: customer.full_name(),
+                order_total: order.total,
+                order_shipping: order.shipping_total,
+                payment_gateway: order.payment_method_title.clone(),
+                shipping_method: order.shipping_lines.first().map(|l| l.method_title.clone()).unwrap_or_default(),
+                shipping_address_line_1: customer.address_1.clone(),
+                shipping_address_line_2: customer.address_2.clone(),
+                shipping_postcode: customer.postcode.clone(),
+                billing_phone_number: order.billing.phone.clone(),
+                _transaction_id: order.transaction_id.clone(),
+                product_name: String::new(),
+                sku: String::new(),
+                quantity: 0,
+                item_price: Money::ZERO,
+            };
+            order.line_items.into_iter()
+                .map(move |item| WooCommerceRow {
+                    product_name: item.full_name(),
+                    sku: item.sku,
+                    quantity: item.quantity,
+                    item_price: item.price,
+                    ..row.clone()
+                })
+        })
+        .collect();
+    Ok(InputData { data, dialect: None, diagnostics: Vec::new() })
+}
+
+#[cfg(test)]
+use crate::catalogue::Catalogue;
+#[cfg(test)]
+use crate::packing::PackingOptions;
+
+#[cfg(test)]
+const ORDERS: &[u8] = include_bytes!("orders.json");
+
+#[test]
+fn test_parse_orders_json() {
+    assert!(is_json(ORDERS));
+    let parsed = parse_orders_json(ORDERS).unwrap();
+    let data = &parsed.data;
+    assert_eq!(data.len(), 10);
+    assert_eq!(data[0].order_id, 5358);
+    assert_eq!(data[0].customer_name, "PERINO LUPO");
+    assert_eq!(data[0].order_date, "2020/05/24");
+    assert_eq!(data[0].sku, "SEL-B-VEG");
+    assert_eq!(data[0].shipping_method, "standard");
+    assert_eq!(data[0].order_shipping, Money::euros(5));
+    assert_eq!(data[0].order_total, Money::from_cents(5710));
+    assert_eq!(data[8].item_price, Money::from_cents(350));
+    assert_eq!(data[9].quantity, 2);
+    // Visible metadata follows the name, unless the name already has it.
+    assert_eq!(data[0].product_name, "SELEZIONE B \"IL VEGETARIANO\" (Formato: Box)");
+    assert_eq!(data[1].product_name, "CARNE TRITA DI MANZO PER RAGU' E POLPETTE 500 g");
+    assert_eq!(data[9].product_name, "CIMA DI RAPA 500 g (Note: solo foglie)");
+
+    let labels = parsed.labels(&PackingOptions::default(), &Catalogue::default());
+    assert_eq!(labels.len(), 3);
+    assert_eq!(&labels[1].delivery, "local pick up");
+    assert_eq!(labels[1].packages.len(), 2);
+}
+
+#[test]
+fn test_reject_invalid_json() {
+    assert!(!is_json(b"Order ID,Product Name"));
+    let err = parse_orders_json(b"[{\"id\": \"not a number\"}]").unwrap_err();
+    assert!(err.to_string().contains("Invalid WooCommerce orders JSON"));
+}
@@ -1,49 +1,131 @@
-use wasm_bindgen::__rt::std::error::Error;
-use csv::{ReaderBuilder};
+use csv::{Position, ReaderBuilder, StringRecord};
 use derive_builder::Builder;
-use anyhow::{Context, Result};
-use wasm_bindgen::__rt::core::fmt::{Display, Formatter};
+use anyhow::Result;
 use wasm_bindgen::__rt::std::collections::HashMap;
-use wasm_bindgen::__rt::core::num::ParseFloatError;
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use crate::barcode::PackageCode;
+use crate::bundles::{BundleComponent, Bundles};
+use crate::catalogue::{self, Catalogue, Temperature};
+use crate::columns::{ColumnMap, Field};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::dialect::{self, Dialect};
+use crate::money::Money;
+use crate::packing::PackingOptions;
+use crate::profiles::ColumnProfile;
+use crate::summary::SummaryRow;
+
+/// The delivery of the orders picked up at the market, rather than shipped.
+const LOCAL_PICKUP: &str = "local pick up";
+
+pub fn parse_csv(data: &[u8], profile: &ColumnProfile) -> Result<InputData> {
+    let (text, dialect) = dialect::decode(data);
+    let dialect = dialect::sniff(&text, dialect);
+    let mut rdr = ReaderBuilder::new()
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .from_reader(text.as_bytes());
+    let header = rdr.headers()?.clone();
+    let records = rdr.records().map(|result| result.map_err(|e| malformed_record(&text, e)));
+    let (data, diagnostics) = parse_records(&header, records, profile)?;
+    Ok(InputData { data, dialect: Some(dialect), diagnostics })
+}
 
-pub fn parse_csv(data: &str) -> Result<InputData> {
-    let reader = ReaderBuilder::new().from_reader(data.as_bytes());
-    let mut rdr = csv::Reader::from(reader);
+/// Builds the rows of an export from its header and records, whatever the format of the file
+/// they were read from. Records with invalid values are left out and reported in the returned
+/// diagnostics; only a header that cannot be used at all fails the whole export.
+pub fn parse_records<I>(header: &StringRecord, records: I, profile: &ColumnProfile) -> Result<(Vec<WooCommerceRow>, Vec<Diagnostic>)>
+where
+    I: IntoIterator<Item = Result<StringRecord, Diagnostic>>,
+{
+    if header.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let columns = ColumnMap::resolve(header, profile)?;
     let mut data = Vec::new();
-    for result in rdr.records() {
-        let ctx = format!("{:?}", &result);
-        let record = result.context(ctx)?;
-
-        data.push(WooCommerceRow {
-            order_id: record[0].parse()?,
-            order_date: record[1].to_owned(),
-            order_status: record[2].to_owned(),
-            customer_name: record[3].to_owned(),
-            order_total: record[4].to_owned(),
-            order_shipping: record[5].parse().with_context(|| format!("Invalid shipping: {}", &record[5]))?,
-            payment_gateway: record[6].to_owned(),
-            shipping_method: record[7].to_owned(),
-            shipping_address_line_1: record[8].to_owned(),
-            shipping_address_line_2: record[9].to_owned(),
-            shipping_postcode: record[10].to_owned(),
-            billing_phone_number: record[11].to_owned(),
-            _transaction_id: record[12].to_owned(),
-            product_name: record[13].to_owned(),
-            quantity: record[14].parse().with_context(|| format!("Invalid quantity: {}", &record[14]))?,
-            item_price: record[15].to_owned(),
+    let mut diagnostics = columns.diagnostics().to_vec();
+    for (i, record) in records.into_iter().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(diagnostic) => {
+                diagnostics.push(diagnostic);
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or(i as u64 + 2);
+        let mut reader = RecordReader { columns: &columns, header, record: &record, line, diagnostics: Vec::new() };
+        let row = WooCommerceRow::from_record(&mut reader);
+        if !reader.diagnostics.iter().any(Diagnostic::is_error) {
+            data.push(row);
+        }
+        diagnostics.append(&mut reader.diagnostics);
+    }
+    Ok((data, diagnostics))
+}
+
+fn malformed_record(text: &str, error: csv::Error) -> Diagnostic {
+    let position = error.position().cloned().unwrap_or_else(Position::new);
+    let raw = text.get(position.byte() as usize..)
+        .and_then(|rest| rest.lines().next())
+        .unwrap_or("")
+        .to_owned();
+    let message = match error.kind() {
+        csv::ErrorKind::UnequalLengths { expected_len, len, .. } =>
+            format!("Expected {} values but found {}", expected_len, len),
+        _ => error.to_string(),
+    };
+    Diagnostic { severity: Severity::Error, line: position.line(), column: None, record: vec![raw.clone()], raw, message }
+}
+
+/// Reads the values of one record, collecting a diagnostic for each one that is not valid.
+struct RecordReader<'a> {
+    columns: &'a ColumnMap,
+    header: &'a StringRecord,
+    record: &'a StringRecord,
+    line: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl RecordReader<'_> {
+    fn text(&self, field: Field) -> String {
+        self.columns.get(self.record, field).to_owned()
+    }
+
+    /// Parses the value of `field`; an empty value reads as `default` if there is one.
+    fn parse<T: FromStr>(&mut self, field: Field, default: Option<T>, what: &str) -> Option<T> {
+        let raw = self.columns.get(self.record, field).trim();
+        match (raw.parse(), default) {
+            (Ok(value), _) => Some(value),
+            (Err(_), Some(default)) if raw.is_empty() => Some(default),
+            (Err(_), _) => {
+                self.report(Severity::Error, field, format!("Invalid {}", what));
+                None
+            }
+        }
+    }
+
+    fn report(&mut self, severity: Severity, field: Field, message: String) {
+        let column = self.columns.index(field)
+            .and_then(|i| self.header.get(i))
+            .unwrap_or_else(|| field.name());
+        self.diagnostics.push(Diagnostic {
+            severity,
+            line: self.line,
+            column: Some(column.to_owned()),
+            raw: self.columns.get(self.record, field).to_owned(),
+            message,
+            record: self.record.iter().map(str::to_owned).collect(),
         });
     }
-    Ok(InputData { data })
 }
 
 #[derive(Builder, Clone, Debug)]
 pub struct WooCommerceRow {
     pub order_id: u32,
     pub order_date: String,
-    pub order_status: String,
     pub customer_name: String,
-    pub order_total: String,
-    pub order_shipping: f32,
+    pub order_total: Money,
+    pub order_shipping: Money,
     pub payment_gateway: String,
     pub shipping_method: String,
     pub shipping_address_line_1: String,
@@ -52,20 +134,62 @@ pub struct WooCommerceRow {
     pub billing_phone_number: String,
     pub _transaction_id: String,
     pub product_name: String,
+    pub sku: String,
     pub quantity: u32,
-    pub item_price: String,
+    pub item_price: Money,
+}
+
+impl WooCommerceRow {
+    fn from_record(reader: &mut RecordReader) -> Self {
+        let order_id = reader.parse(Field::OrderId, None, "order ID");
+        let order_total = reader.parse(Field::OrderTotal, None, "order total");
+        let order_shipping = reader.parse(Field::OrderShipping, Some(Money::ZERO), "shipping");
+        let quantity = reader.parse(Field::Quantity, None, "quantity");
+        let item_price = reader.parse(Field::ItemPrice, None, "price");
+        if reader.text(Field::ProductName).trim().is_empty() {
+            reader.report(Severity::Warning, Field::ProductName, "Missing product name".to_owned());
+        }
+        if quantity == Some(0) {
+            reader.report(Severity::Warning, Field::Quantity, "Quantity is zero".to_owned());
+        }
+        if reader.text(Field::CustomerName).trim().is_empty() {
+            reader.report(Severity::Warning, Field::CustomerName, "Missing customer name".to_owned());
+        }
+
+        WooCommerceRow {
+            order_id: order_id.unwrap_or_default(),
+            order_date: reader.text(Field::OrderDate),
+            customer_name: reader.text(Field::CustomerName),
+            order_total: order_total.unwrap_or_default(),
+            order_shipping: order_shipping.unwrap_or_default(),
+            payment_gateway: reader.text(Field::PaymentGateway),
+            shipping_method: reader.text(Field::ShippingMethod),
+            shipping_address_line_1: reader.text(Field::ShippingAddressLine1),
+            shipping_address_line_2: reader.text(Field::ShippingAddressLine2),
+            shipping_postcode: reader.text(Field::ShippingPostcode),
+            billing_phone_number: reader.text(Field::BillingPhoneNumber),
+            _transaction_id: reader.text(Field::TransactionId),
+            product_name: reader.text(Field::ProductName),
+            sku: reader.text(Field::Sku),
+            quantity: quantity.unwrap_or_default(),
+            item_price: item_price.unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct InputData {
-    pub data: Vec<WooCommerceRow>
+    pub data: Vec<WooCommerceRow>,
+    pub dialect: Option<Dialect>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Clone, Builder)]
 pub struct OrderDetails {
     pub order_id: u32,
     pub customer_name: String,
-    pub order_total: Price,
+    pub order_total: Money,
+    pub shipping: Money,
     pub delivery: String,
     pub payment_gateway: String,
     pub shipping_address_line_1: String,
@@ -84,6 +208,11 @@ pub struct DeliveryDetail {
 }
 
 impl OrderDetails {
+    /// Whether the customer picks the order up at the market, so it is not on the delivery sheet.
+    pub fn is_pickup(&self) -> bool {
+        self.delivery == LOCAL_PICKUP
+    }
+
     pub fn delivery_details(&self, i: usize) -> Box<[DeliveryDetail]> {
         let show_totals = i + 1 == self.packages.len();
         let mut details = Vec::new();
@@ -92,42 +221,105 @@ impl OrderDetails {
             details.append(&mut vec![
                 DeliveryDetail { name: "Consegna", data: self.delivery.clone(), highlight: false },
                 DeliveryDetail { name: "Metodo  Pagamento", data: self.payment_gateway.clone(), highlight: false },
-                DeliveryDetail { name: "Totale", data: format!("{}€", self.order_total.display), highlight: false },
+                DeliveryDetail { name: "Totale", data: format!("{} €", self.order_total), highlight: false },
             ]);
         }
 
+        if let Some(weight) = self.package_weight(i) {
+            let unknown = self.packages[i].iter().any(|item| item.weight.is_none());
+            let data = format!("{}{} kg", if unknown { "≥ " } else { "" }, catalogue::format_thousandths(weight));
+            details.push(DeliveryDetail { name: "Peso", data, highlight: false });
+        }
+
         let packages_str = if self.packages.len() > 1 { "Colli" } else { "Collo" };
         details.push(DeliveryDetail { name: "", data: format!("{} {}", self.packages.len(), packages_str), highlight: true });
 
         details.into_boxed_slice()
     }
 
-    fn package_name(&self, index: usize) -> &str {
-        if index == 0 {
-            ""
-        } else {
-            &self.customer_name
+    /// The items of all the packages, for the packing slip: one line for each product, with the
+    /// units in all the packages and the numbers of the packages holding them, from 1.
+    pub fn slip_lines(&self) -> Vec<SlipLine> {
+        let mut lines: Vec<SlipLine> = Vec::new();
+        for (i, item) in self.packages.iter().enumerate().flat_map(|(i, items)| items.iter().map(move |item| (i + 1, item))) {
+            let line = match lines.iter_mut().position(|line| line.product_name == item.product_name) {
+                Some(position) => &mut lines[position],
+                None => {
+                    lines.push(SlipLine { product_name: item.product_name.clone(), ..SlipLine::default() });
+                    lines.last_mut().unwrap()
+                }
+            };
+            line.quantity += item.quantity;
+            if !line.packages.contains(&i) {
+                line.packages.push(i);
+            }
+            for component in &item.components {
+                match line.components.iter_mut().find(|c| c.product_name == component.product_name) {
+                    Some(c) => c.quantity += component.quantity,
+                    None => line.components.push(component.clone()),
+                }
+            }
         }
+        lines
+    }
+
+    /// What the barcodes on the label of the `i`-th package say.
+    pub fn package_code(&self, i: usize) -> PackageCode {
+        PackageCode { order_id: self.order_id, package: i + 1, packages: self.packages.len() }
+    }
+
+    /// The temperature class of the `i`-th package, which all its items share; `None` if they
+    /// have none, and the label has no banner.
+    pub fn package_temperature(&self, i: usize) -> Option<Temperature> {
+        self.packages[i].first().and_then(|item| item.temperature)
+    }
+
+    /// Grams in the `i`-th package, counting the products whose weight is known, or `None` if
+    /// none is.
+    pub fn package_weight(&self, i: usize) -> Option<u32> {
+        let items = &self.packages[i];
+        if items.iter().all(|item| item.weight.is_none()) {
+            return None;
+        }
+        Some(items.iter().map(|item| item.weight.unwrap_or(0) * item.quantity).sum())
     }
 }
 
-#[derive(Clone, Builder)]
-pub struct OrderItem {
+/// A product of an order on its packing slip, see `OrderDetails::slip_lines`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlipLine {
     pub product_name: String,
+    /// Units in all the packages.
     pub quantity: u32,
-    pub item_price: f32,
+    /// The numbers of the packages holding the product, from 1.
+    pub packages: Vec<usize>,
+    /// For a bundle, its components, if they are to be listed on the labels.
+    pub components: Vec<BundleComponent>,
 }
 
 #[derive(Clone, Builder)]
-pub struct SummaryRow {
+pub struct OrderItem {
     pub product_name: String,
-    pub total_quantity: u32,
+    /// The name printed on the label, see `ShortNames`.
+    pub label_name: String,
+    /// For a bundle, its components in this package, if they are to be listed on the label.
+    pub components: Vec<BundleComponent>,
+    /// Units in this package.
+    pub quantity: u32,
+    /// Units ordered on the line; more than `quantity` when the line is split between packages.
+    pub ordered: u32,
+    pub item_price: Money,
+    /// Grams per unit, if the product is in the catalogue with a weight.
+    pub weight: Option<u32>,
+    /// `None` if neither the catalogue nor the temperature rules give it.
+    pub temperature: Option<Temperature>,
 }
 
 impl InputData {
-    pub fn labels(&self, multipack: bool) -> Result<Vec<OrderDetails>> {
+    pub fn labels(&self, options: &PackingOptions, catalogue: &Catalogue) -> Vec<OrderDetails> {
         use itertools::Itertools;
 
+        let products = catalogue.index(&self.data);
         let mut result = Vec::new();
         for (order_id, rows) in &self.data.iter().group_by(|row| row.order_id) {
             let rows = rows.collect::<Vec<&WooCommerceRow>>();
@@ -141,34 +333,27 @@ impl InputData {
                 billing_phone_number: row.billing_phone_number.clone(),
                 payment_gateway: row.payment_gateway.clone(),
                 order_date: row.order_date.clone(),
-                order_total: Price::parse(&row.order_total)?,
+                order_total: row.order_total,
+                shipping: row.order_shipping,
                 delivery: Self::map_shipping_to_delivery(row.order_shipping, &row.shipping_method),
                 packages: Vec::new(),
             };
-            let num_packages = {
-                let val = order_details.order_total.value;
-                if val <= 40.0 || !multipack {
-                    1
-                } else if val <= 70.0 {
-                    2
-                } else if val <= 80.0 {
-                    3
-                } else {
-                    4
-                }
-            };
-            let items_per_package = calculate_items_per_package(rows.len() as i32, num_packages as i32);
-
-            for p in &rows.into_iter()
-                .sorted_by_key(|r| &r.product_name)
-                .chunks(items_per_package as usize)
-            {
+            for p in options.split(rows, &products) {
                 let mut package_items = Vec::new();
-                for o in p.into_iter() {
+                for portion in p.into_iter() {
+                    let o = portion.row;
                     package_items.push(OrderItem {
-                        quantity: o.quantity,
+                        quantity: portion.quantity,
+                        ordered: o.quantity,
                         product_name: o.product_name.clone(),
-                        item_price: o.item_price.parse().with_context(|| format!("Invalid price: {}", o.item_price))?,
+                        label_name: products.label_name(o),
+                        components: match catalogue.bundles.find(&o.product_name) {
+                            Some(_) if catalogue.bundles.breakdown_on_labels => catalogue.bundles.expand(&o.product_name, portion.quantity),
+                            _ => Vec::new(),
+                        },
+                        item_price: o.item_price,
+                        weight: products.product(o).map(|product| product.weight).filter(|weight| *weight > 0),
+                        temperature: products.temperature(o),
                     })
                 }
                 order_details.packages.push(package_items);
@@ -176,106 +361,106 @@ impl InputData {
             result.push(order_details);
         }
 
-        Ok(result)
+        result
     }
 
-    pub fn summary(&self) -> Vec<(String, u32)> {
-        use itertools::Itertools;
-
-        let mut result: HashMap<String, u32> = HashMap::new();
+    /// The totals of each product, sorted by name. With `bundles.expand`, bundles are counted as
+    /// their components, and the revenue of a bundle is shared between them by number of units.
+    pub fn summary(&self, bundles: &Bundles) -> Vec<SummaryRow> {
+        let mut summary: BTreeMap<String, SummaryRow> = BTreeMap::new();
+        let mut orders: HashMap<String, HashSet<u32>> = HashMap::new();
         for row in &self.data {
-            *result.entry(row.product_name.clone()).or_insert(0) += 1;
+            let components = if bundles.expand {
+                bundles.expand(&row.product_name, row.quantity)
+            } else {
+                vec![BundleComponent { product_name: row.product_name.clone(), quantity: row.quantity }]
+            };
+            let revenues = (row.item_price * row.quantity).split(&components.iter().map(|c| c.quantity).collect::<Vec<_>>());
+            let delivery = if row.shipping_method.is_empty() { "-" } else { &row.shipping_method };
+            for (component, revenue) in components.into_iter().zip(revenues) {
+                let product = summary.entry(component.product_name.clone())
+                    .or_insert_with(|| SummaryRow { product_name: component.product_name.clone(), ..SummaryRow::default() });
+                product.total_quantity += component.quantity;
+                product.revenue += revenue;
+                *product.by_delivery.entry(delivery.to_owned()).or_insert(0) += component.quantity;
+                orders.entry(component.product_name).or_default().insert(row.order_id);
+            }
         }
-        result.into_iter()
-            .sorted_by(|t1, t2| t1.0.cmp(&t2.0))
+        summary.into_values()
+            .map(|product| SummaryRow { orders: orders[&product.product_name].len() as u32, ..product })
             .collect()
     }
 
-    fn map_shipping_to_delivery(order_shipping: f32, shipping_method: &str) -> String {
-        if shipping_method.to_ascii_lowercase() == "ritiro presso il mercato" {
-            "local pick up".to_owned()
+    fn map_shipping_to_delivery(order_shipping: Money, shipping_method: &str) -> String {
+        if shipping_method.eq_ignore_ascii_case("ritiro presso il mercato") {
+            LOCAL_PICKUP.to_owned()
         } else {
             format!("{} €", order_shipping)
         }
     }
 }
 
-fn calculate_items_per_package(num_rows: i32, num_packages: i32) -> usize {
-    let (mut items_per_package, remainder) = (num_rows / num_packages, num_rows % num_packages);
-    if remainder > 0 {
-        items_per_package += 1;
-    }
-    items_per_package as usize
-}
-
-#[derive(Debug)]
-pub struct PriceParseError(String);
-
-impl Display for PriceParseError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
-    }
-}
-
-impl Error for PriceParseError {}
-
-#[derive(Clone)]
-pub struct Price {
-    pub value: f32,
-    pub display: String,
-}
+#[cfg(test)]
+const DATA: &str = include_str!("data.csv");
 
-impl Price {
-    fn parse(s: &str) -> Result<Self, ParseFloatError> {
-        use core::str::FromStr;
-        Ok(Price {
-            display: s.to_string(),
-            value: f32::from_str(&s.replace(',', "."))?,
-        })
-    }
+/// The rows of `data.csv`, for the tests of the other modules.
+#[cfg(test)]
+pub fn sample_data() -> InputData {
+    parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap()
 }
 
+/// The orders of `data.csv`, packed with the default options and no catalogue.
 #[cfg(test)]
-const DATA: &str = include_str!("data.csv");
+pub fn sample_orders() -> Vec<OrderDetails> {
+    sample_data().labels(&PackingOptions::default(), &Catalogue::default())
+}
 
 #[test]
 fn test_parse_csv() {
-    let parsed = parse_csv(DATA).unwrap();
+    let parsed = parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap();
     let data = &parsed.data;
     assert_eq!(data.len(), 10);
     assert_eq!(data[0].order_id, 5358);
-    assert_eq!(data[8].item_price, "3.5");
+    assert_eq!(data[8].item_price, Money::from_cents(350));
+    assert_eq!(data[0].order_total, Money::from_cents(5710));
 
-    let labels = parsed.labels(true).unwrap();
+    let labels = parsed.labels(&PackingOptions::default(), &Catalogue::default());
     assert_eq!(labels.len(), 3);
     assert_eq!(labels[0].order_id, 5358);
     assert_eq!(labels[0].packages[0].len(), 2);
     assert_eq!(labels[0].packages[1].len(), 2);
-    assert_eq!(&labels[0].delivery, "5 €");
+    assert_eq!(&labels[0].delivery, "5,00 €");
     assert_eq!(labels[0].packages[1][1].product_name, r#"SELEZIONE B "IL VEGETARIANO""#);
-    assert_eq!(labels[0].packages[1][1].item_price, 40.0);
+    assert_eq!(labels[0].packages[1][1].item_price, Money::euros(40));
     assert_eq!(labels[0].packages[1][1].quantity, 1);
     assert_eq!(labels[0].packages[1][0].product_name, r#"GALLETTO VALLE SPLUGA ALLE ERBE DI MONTAGNA 500 g"#);
-    assert_eq!(labels[0].packages[1][0].item_price, 4.6);
+    assert_eq!(labels[0].packages[1][0].item_price, Money::from_cents(460));
     assert_eq!(labels[0].packages[1][0].quantity, 1);
 
     assert_eq!(labels[1].order_id, 5357);
     assert_eq!(&labels[1].delivery, "local pick up");
+    assert!(labels[1].is_pickup() && !labels[0].is_pickup());
     assert_eq!(labels[1].packages[0].len(), 3);
     assert_eq!(labels[1].packages[1].len(), 2);
 
-    let summary = parsed.summary();
+    let summary = parsed.summary(&Bundles::default());
     assert_eq!(summary.len(), 9);
-    assert_eq!(summary.iter().find(|(key, _)| key == r#"SELEZIONE B "IL VEGETARIANO""#).unwrap().1, 2);
+    let selection = summary.iter().find(|row| row.product_name == r#"SELEZIONE B "IL VEGETARIANO""#).unwrap();
+    assert_eq!((selection.total_quantity, selection.orders, selection.revenue), (2, 2, Money::euros(80)));
+    assert_eq!(selection.by_delivery.get("standard"), Some(&1));
+    assert_eq!(selection.by_delivery.get("Ritiro presso il mercato"), Some(&1));
     let second = &summary[1];
-    assert_eq!(second.0, "CARNE TRITA DI MANZO PER RAGU' E POLPETTE 500 g");
+    assert_eq!(second.product_name, "CARNE TRITA DI MANZO PER RAGU' E POLPETTE 500 g");
+    // Two bunches in one order line.
+    let turnip_tops = summary.iter().find(|row| row.product_name == "CIMA DI RAPA 500 g").unwrap();
+    assert_eq!((turnip_tops.total_quantity, turnip_tops.orders, turnip_tops.revenue), (2, 1, Money::from_cents(340)));
 
-    assert_eq!(labels[2].delivery, "0 €")
+    assert_eq!(labels[2].delivery, "0,00 €")
 }
 
 #[test]
 fn test_parse_empty_data() {
-    let parsed = parse_csv("").unwrap();
+    let parsed = parse_csv("".as_bytes(), &ColumnProfile::default()).unwrap();
     assert_eq!(parsed.data.len(), 0);
 }
 
@@ -284,15 +469,145 @@ const BIG_DATA: &str = include_str!("data-big.csv");
 
 #[test]
 fn test_multiple_packages() {
-    let parsed = parse_csv(BIG_DATA).unwrap().labels(true).unwrap();
+    let parsed = parse_csv(BIG_DATA.as_bytes(), &ColumnProfile::default()).unwrap().labels(&PackingOptions::default(), &Catalogue::default());
     let must_have_4_packages = &parsed[3];
     assert_eq!(must_have_4_packages.packages.len(), 4);
 }
 
 #[test]
 fn test_no_multipack() {
-    let parsed = parse_csv(BIG_DATA).unwrap().labels(false).unwrap();
-    for i in 0..4 {
-        assert_eq!(parsed[i].packages.len(), 1);
+    let parsed = parse_csv(BIG_DATA.as_bytes(), &ColumnProfile::default()).unwrap().labels(&PackingOptions { multipack: false, ..PackingOptions::default() }, &Catalogue::default());
+    for order in &parsed[0..4] {
+        assert_eq!(order.packages.len(), 1);
     }
 }
+
+#[test]
+fn test_packages_by_weight() {
+    let mut catalogue = Catalogue::default();
+    catalogue.set_text("Name;Weight;Volume\n\"SELEZIONE B \"\"IL VEGETARIANO\"\"\";6;10\nCARNE TRITA DI MANZO PER RAGU' E POLPETTE 500 g;0,5;0,5\nFETTINE DI LONZA DI SUINO 500 g;0,5;0,5").unwrap();
+    let options = PackingOptions { max_weight: Some(6000), ..PackingOptions::default() };
+    let labels = parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap().labels(&options, &catalogue);
+    let order = &labels[0];
+    assert_eq!(order.packages.len(), 2);
+    assert_eq!(order.packages[0].len(), 1);
+    assert_eq!(order.package_weight(0), Some(6000));
+    assert_eq!(order.package_weight(1), Some(1000));
+    let weight = |i| order.delivery_details(i).iter().find(|d| d.name == "Peso").map(|d| d.data.clone());
+    assert_eq!(weight(0).as_deref(), Some("6 kg"));
+    // The galletto is not in the catalogue, so the weight is a lower bound.
+    assert_eq!(weight(1).as_deref(), Some("≥ 1 kg"));
+    assert_eq!(labels[2].package_weight(0), None);
+}
+
+#[test]
+fn test_short_names_on_labels_only() {
+    let mut catalogue = Catalogue::default();
+    catalogue.short_names.drop_units = true;
+    catalogue.short_names.set_mappings_text("SELEZIONE B \"IL VEGETARIANO\" => SELEZIONE VEG.");
+    let parsed = parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap();
+    let labels = parsed.labels(&PackingOptions::default(), &catalogue);
+    let items = labels[0].packages.iter().flatten().collect::<Vec<_>>();
+    assert!(items.iter().any(|item| item.label_name == "SELEZIONE VEG."));
+    assert!(items.iter().any(|item| item.label_name == "FETTINE DI LONZA DI SUINO"));
+    assert!(parsed.summary(&catalogue.bundles).iter().any(|row| row.product_name == "FETTINE DI LONZA DI SUINO 500 g"));
+}
+
+#[test]
+fn test_bundles() {
+    let mut catalogue = Catalogue::default();
+    catalogue.bundles.set_text("SELEZIONE B \"IL VEGETARIANO\"\n    2 ZUCCHINE 500 g\n    1 YOGURT DI CAPRA 500 g");
+    let parsed = parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap();
+    let count = |summary: Vec<SummaryRow>, product: &str| summary.into_iter().find(|row| row.product_name == product).map(|row| row.total_quantity);
+
+    assert_eq!(count(parsed.summary(&catalogue.bundles), "SELEZIONE B \"IL VEGETARIANO\""), Some(2));
+    catalogue.bundles.expand = true;
+    assert_eq!(count(parsed.summary(&catalogue.bundles), "SELEZIONE B \"IL VEGETARIANO\""), None);
+    assert_eq!(count(parsed.summary(&catalogue.bundles), "ZUCCHINE 500 g"), Some(4));
+    // One yogurt ordered on its own, and one in each of the two bundles.
+    assert_eq!(count(parsed.summary(&catalogue.bundles), "YOGURT DI CAPRA 500 g"), Some(3));
+    let zucchini = parsed.summary(&catalogue.bundles).into_iter().find(|row| row.product_name == "ZUCCHINE 500 g").unwrap();
+    assert_eq!((zucchini.orders, zucchini.revenue), (2, Money::from_cents(2 * 2666)));
+
+    let bundle = |catalogue: &Catalogue| parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap()
+        .labels(&PackingOptions::default(), catalogue)[0].packages.iter().flatten()
+        .find(|item| item.product_name.starts_with("SELEZIONE"))
+        .map(|item| item.components.len());
+    assert_eq!(bundle(&catalogue), Some(0));
+    catalogue.bundles.breakdown_on_labels = true;
+    assert_eq!(bundle(&catalogue), Some(2));
+}
+
+#[test]
+fn test_slip_lines() {
+    use crate::packing::{Condition, PackRule};
+
+    let parsed = parse_csv(DATA.as_bytes(), &ColumnProfile::default()).unwrap();
+    let labels = parsed.labels(&PackingOptions::default(), &Catalogue::default());
+    let lines = labels[0].slip_lines();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines.iter().map(|line| line.quantity).sum::<u32>(), labels[0].packages.iter().flatten().map(|item| item.quantity).sum::<u32>());
+
+    let options = PackingOptions { multipack: true, rules: vec![PackRule { condition: Condition::Always, packages: 2 }], split_lines: true, ..PackingOptions::default() };
+    let labels = parsed.labels(&options, &Catalogue::default());
+    let cima = labels.iter().find(|order| order.order_id == 11099).unwrap();
+    assert_eq!(cima.packages.len(), 2);
+    assert_eq!(cima.slip_lines(), vec![SlipLine { product_name: "CIMA DI RAPA 500 g".to_owned(), quantity: 2, packages: vec![1, 2], components: Vec::new() }]);
+}
+
+#[test]
+fn test_parse_reordered_and_extra_columns() {
+    let data = "Prodotto,Note,Quantità,Prezzo Articolo IVA Esclusa,Numero Ordine,Cliente,Totale Ordine\n\
+                MIELE DI ACACIA 500 g,fragile,2,7.5,6001,Rossi,\"15,00\"\n";
+    let parsed = parse_csv(data.as_bytes(), &ColumnProfile::default()).unwrap();
+    let row = &parsed.data[0];
+    assert_eq!(row.order_id, 6001);
+    assert_eq!(row.product_name, "MIELE DI ACACIA 500 g");
+    assert_eq!(row.quantity, 2);
+    assert_eq!(row.item_price, Money::from_cents(750));
+    assert_eq!(row.order_total, Money::euros(15));
+    assert_eq!(row.order_shipping, Money::ZERO);
+    assert_eq!(row.shipping_postcode, "");
+}
+
+#[test]
+fn test_parse_missing_columns() {
+    let err = parse_csv("Order ID,Product Name\n1,MIELE\n".as_bytes(), &ColumnProfile::default()).unwrap_err();
+    assert!(err.to_string().contains("Customer Name"));
+    assert!(err.to_string().contains("Quantity of items purchased"));
+}
+
+#[test]
+fn test_parse_excel_semicolon_export() {
+    let data = DATA.replace(',', ";").replace("\"57;10\"", "57,10").replace("\"57;90\"", "57,90").replace("80.20", "80,20");
+    let parsed = parse_csv(data.as_bytes(), &ColumnProfile::default()).unwrap();
+    assert_eq!(parsed.data.len(), 10);
+    assert_eq!(parsed.data[0].order_total, Money::from_cents(5710));
+    assert_eq!(parsed.dialect.unwrap().delimiter, b';');
+}
+
+#[test]
+fn test_parse_reports_bad_rows_and_keeps_good_ones() {
+    let data = "Order ID,Customer Name,Order Total,Product Name,Quantity,Item Price\n\
+                1,Rossi,10,MIELE,2,5\n\
+                2,Bianchi,12,PANE,due,3\n\
+                3,Verdi,8\n\
+                4,,9,,0,4.5\n";
+    let parsed = parse_csv(data.as_bytes(), &ColumnProfile::default()).unwrap();
+    assert_eq!(parsed.data.iter().map(|r| r.order_id).collect::<Vec<_>>(), vec![1, 4]);
+
+    let diagnostics = &parsed.diagnostics;
+    assert_eq!(diagnostics.len(), 5);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].line, 3);
+    assert_eq!(diagnostics[0].column.as_deref(), Some("Quantity"));
+    assert_eq!(diagnostics[0].raw, "due");
+    assert_eq!(diagnostics[0].record, vec!["2", "Bianchi", "12", "PANE", "due", "3"]);
+
+    assert_eq!(diagnostics[1].line, 4);
+    assert_eq!(diagnostics[1].column, None);
+    assert_eq!(diagnostics[1].raw, "3,Verdi,8");
+    assert_eq!(diagnostics[1].message, "Expected 6 values but found 3");
+
+    assert!(diagnostics[2..].iter().all(|d| d.severity == Severity::Warning && d.line == 5));
+}